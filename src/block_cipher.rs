@@ -0,0 +1,643 @@
+// Mode-generic AES wrapper shared by every challenge that needs a block cipher
+
+pub use block_cipher::*;
+
+#[allow(clippy::module_inception)]
+pub mod block_cipher {
+    pub use crate::error::CryptopalsError;
+    use ::aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+    pub use ::aes::Block;
+    pub use ::aes::{Aes128, Aes192, Aes256};
+    use std::fmt;
+
+    pub const BLOCK_SIZE_BYTES: usize = 16;
+
+    /// Pads to a multiple of `block_size` with PKCS#7, always adding at least one byte
+    pub fn pkcs_padding(input: &[u8], block_size: usize) -> Vec<u8> {
+        let mut output = input.to_vec();
+        let padding = block_size - (input.len() % block_size);
+        output.extend(vec![padding as u8; padding]);
+        output
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum PaddingError {
+        /// Padded input must be non-empty and a multiple of the block size
+        InvalidLength(usize),
+        /// The last byte is zero or bigger than a block
+        InvalidPadByte(u8),
+        /// The pad bytes don't all equal the pad length
+        InconsistentPadding,
+    }
+
+    impl fmt::Display for PaddingError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                PaddingError::InvalidLength(len) => write!(f, "invalid padded length: {}", len),
+                PaddingError::InvalidPadByte(byte) => write!(f, "invalid pad byte: {}", byte),
+                PaddingError::InconsistentPadding => write!(f, "inconsistent pad bytes"),
+            }
+        }
+    }
+
+    impl std::error::Error for PaddingError {}
+
+    /// Strictly validates PKCS#7 padding and returns the input without it
+    pub fn pkcs_unpadding(input: &[u8], block_size: usize) -> Result<&[u8], PaddingError> {
+        if input.is_empty() || !input.len().is_multiple_of(block_size) {
+            return Err(PaddingError::InvalidLength(input.len()));
+        }
+
+        let pad_byte = input[input.len() - 1];
+        if pad_byte == 0 || pad_byte as usize > block_size {
+            return Err(PaddingError::InvalidPadByte(pad_byte));
+        }
+
+        let (unpadded, padding) = input.split_at(input.len() - pad_byte as usize);
+        if padding.iter().any(|byte| *byte != pad_byte) {
+            return Err(PaddingError::InconsistentPadding);
+        }
+
+        Ok(unpadded)
+    }
+
+    /// The raw AES block permutation, whatever the key size
+    pub trait AesCore: Sized {
+        fn from_key(key: &[u8]) -> Result<Self, CryptopalsError>;
+        fn encrypt_block(&self, block: &mut Block);
+        fn decrypt_block(&self, block: &mut Block);
+        fn encrypt_blocks(&self, blocks: &mut [Block]);
+        fn decrypt_blocks(&self, blocks: &mut [Block]);
+    }
+
+    macro_rules! impl_aes_core {
+        ($($aes:ty),*) => {$(
+            impl AesCore for $aes {
+                fn from_key(key: &[u8]) -> Result<Self, CryptopalsError> {
+                    <$aes>::new_from_slice(key).map_err(|_| CryptopalsError::KeyLength(key.len()))
+                }
+
+                fn encrypt_block(&self, block: &mut Block) {
+                    BlockEncrypt::encrypt_block(self, block)
+                }
+
+                fn decrypt_block(&self, block: &mut Block) {
+                    BlockDecrypt::decrypt_block(self, block)
+                }
+
+                fn encrypt_blocks(&self, blocks: &mut [Block]) {
+                    BlockEncrypt::encrypt_blocks(self, blocks)
+                }
+
+                fn decrypt_blocks(&self, blocks: &mut [Block]) {
+                    BlockDecrypt::decrypt_blocks(self, blocks)
+                }
+            }
+        )*};
+    }
+
+    impl_aes_core!(Aes128, Aes192, Aes256);
+
+    /// AES with the key size picked at runtime from the length of the key
+    #[derive(Clone)]
+    pub enum AnyAes {
+        Aes128(Aes128),
+        Aes192(Aes192),
+        Aes256(Aes256),
+    }
+
+    impl AnyAes {
+        pub fn key_size_bits(&self) -> usize {
+            match self {
+                AnyAes::Aes128(_) => 128,
+                AnyAes::Aes192(_) => 192,
+                AnyAes::Aes256(_) => 256,
+            }
+        }
+    }
+
+    macro_rules! dispatch {
+        ($self:ident, $cipher:ident => $body:expr) => {
+            match $self {
+                AnyAes::Aes128($cipher) => $body,
+                AnyAes::Aes192($cipher) => $body,
+                AnyAes::Aes256($cipher) => $body,
+            }
+        };
+    }
+
+    impl AesCore for AnyAes {
+        fn from_key(key: &[u8]) -> Result<Self, CryptopalsError> {
+            match key.len() {
+                16 => Aes128::from_key(key).map(AnyAes::Aes128),
+                24 => Aes192::from_key(key).map(AnyAes::Aes192),
+                32 => Aes256::from_key(key).map(AnyAes::Aes256),
+                len => Err(CryptopalsError::KeyLength(len)),
+            }
+        }
+
+        fn encrypt_block(&self, block: &mut Block) {
+            dispatch!(self, cipher => AesCore::encrypt_block(cipher, block))
+        }
+
+        fn decrypt_block(&self, block: &mut Block) {
+            dispatch!(self, cipher => AesCore::decrypt_block(cipher, block))
+        }
+
+        fn encrypt_blocks(&self, blocks: &mut [Block]) {
+            dispatch!(self, cipher => AesCore::encrypt_blocks(cipher, blocks))
+        }
+
+        fn decrypt_blocks(&self, blocks: &mut [Block]) {
+            dispatch!(self, cipher => AesCore::decrypt_blocks(cipher, blocks))
+        }
+    }
+
+    /// A mode of operation. Modes only describe how blocks are chained together,
+    /// the raw AES permutation is handed to them by `BlockCipher`.
+    /// `out` is always at least as long as the input.
+    pub trait BlockMode {
+        fn encrypt<C: AesCore>(&self, cipher: &C, plaintext: &[Block], out: &mut [Block]);
+        fn decrypt<C: AesCore>(&self, cipher: &C, ciphertext: &[Block], out: &mut [Block]);
+
+        /// Keystream block number `index` for modes that behave like a stream cipher,
+        /// `None` for modes that don't
+        fn keystream_block<C: AesCore>(&self, _cipher: &C, _index: u64) -> Option<Block> {
+            None
+        }
+
+        /// Stream-like modes encrypt arbitrary lengths and are never padded
+        fn is_stream_mode(&self) -> bool {
+            false
+        }
+
+        /// Moves the mode past `ciphertext` so the next call carries on where it ended,
+        /// eg. CBC chains from the last ciphertext block
+        fn advance(&mut self, _ciphertext: &[Block]) {}
+    }
+
+    /// Electronic Code Book
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Ecb;
+
+    impl BlockMode for Ecb {
+        fn encrypt<C: AesCore>(&self, cipher: &C, plaintext: &[Block], out: &mut [Block]) {
+            let out = &mut out[..plaintext.len()];
+            out.copy_from_slice(plaintext);
+
+            // parallel encrypt
+            cipher.encrypt_blocks(out);
+        }
+
+        fn decrypt<C: AesCore>(&self, cipher: &C, ciphertext: &[Block], out: &mut [Block]) {
+            let out = &mut out[..ciphertext.len()];
+            out.copy_from_slice(ciphertext);
+
+            // parallel decrypt
+            cipher.decrypt_blocks(out);
+        }
+    }
+
+    /// Cipher Block Chaining, seeded with an initialization vector
+    #[derive(Debug, Clone, Copy)]
+    pub struct Cbc {
+        pub iv: Block,
+    }
+
+    impl Cbc {
+        pub fn new(iv: Block) -> Self {
+            Cbc { iv }
+        }
+    }
+
+    impl BlockMode for Cbc {
+        fn encrypt<C: AesCore>(&self, cipher: &C, plaintext: &[Block], out: &mut [Block]) {
+            let mut prev_block = self.iv;
+
+            for (plaintext_block, out_block) in plaintext.iter().zip(out.iter_mut()) {
+                // xor plaintext with prev block/iv
+                for ((out, plain), prev) in out_block
+                    .iter_mut()
+                    .zip(plaintext_block.iter())
+                    .zip(prev_block.iter())
+                {
+                    *out = *plain ^ *prev;
+                }
+
+                cipher.encrypt_block(out_block);
+                prev_block = *out_block;
+            }
+        }
+
+        fn decrypt<C: AesCore>(&self, cipher: &C, ciphertext: &[Block], out: &mut [Block]) {
+            let mut prev_block = self.iv;
+
+            for (ciphertext_block, out_block) in ciphertext.iter().zip(out.iter_mut()) {
+                out_block.copy_from_slice(ciphertext_block);
+                cipher.decrypt_block(out_block);
+
+                // xor output with prev block/iv
+                for (out, prev) in out_block.iter_mut().zip(prev_block.iter()) {
+                    *out ^= *prev;
+                }
+                prev_block = *ciphertext_block;
+            }
+        }
+
+        fn advance(&mut self, ciphertext: &[Block]) {
+            if let Some(last) = ciphertext.last() {
+                self.iv = *last;
+            }
+        }
+    }
+
+    /// How the nonce and block counter are laid out in each CTR input block
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CounterLayout {
+        /// 64-bit little-endian nonce followed by a 64-bit little-endian block counter (cryptopals)
+        LittleEndian64(u64),
+        /// 96-bit nonce followed by a 32-bit big-endian block counter (GCM)
+        BigEndian96([u8; 12]),
+    }
+
+    /// Counter mode, turns the block cipher into a stream cipher so no padding is needed
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Ctr {
+        pub layout: CounterLayout,
+        /// counter value of the first block
+        pub initial_counter: u64,
+    }
+
+    impl Ctr {
+        pub fn new(layout: CounterLayout) -> Self {
+            Ctr {
+                layout,
+                initial_counter: 0,
+            }
+        }
+
+        /// The layout used by the cryptopals challenges
+        pub fn cryptopals(nonce: u64) -> Self {
+            Ctr::new(CounterLayout::LittleEndian64(nonce))
+        }
+
+        /// The layout used by GCM; GCM itself starts encrypting at counter 2
+        pub fn gcm(nonce: [u8; 12]) -> Self {
+            Ctr::new(CounterLayout::BigEndian96(nonce))
+        }
+
+        pub fn with_initial_counter(mut self, initial_counter: u64) -> Self {
+            self.initial_counter = initial_counter;
+            self
+        }
+
+        /// The block that gets encrypted to produce keystream block number `index`
+        pub fn counter_block(&self, index: u64) -> Block {
+            let counter = self.initial_counter.wrapping_add(index);
+            let mut block = Block::default();
+
+            match self.layout {
+                CounterLayout::LittleEndian64(nonce) => {
+                    block[..8].copy_from_slice(&nonce.to_le_bytes());
+                    block[8..].copy_from_slice(&counter.to_le_bytes());
+                }
+                CounterLayout::BigEndian96(nonce) => {
+                    // the 32-bit counter wraps around
+                    block[..12].copy_from_slice(&nonce);
+                    block[12..].copy_from_slice(&(counter as u32).to_be_bytes());
+                }
+            }
+            block
+        }
+    }
+
+    impl BlockMode for Ctr {
+        fn encrypt<C: AesCore>(&self, cipher: &C, plaintext: &[Block], out: &mut [Block]) {
+            let out = &mut out[..plaintext.len()];
+
+            // generate the keystream for every block at once
+            for (index, out_block) in out.iter_mut().enumerate() {
+                *out_block = self.counter_block(index as u64);
+            }
+            cipher.encrypt_blocks(out);
+
+            for (out_block, plaintext_block) in out.iter_mut().zip(plaintext.iter()) {
+                for (out, plain) in out_block.iter_mut().zip(plaintext_block.iter()) {
+                    *out ^= *plain;
+                }
+            }
+        }
+
+        fn decrypt<C: AesCore>(&self, cipher: &C, ciphertext: &[Block], out: &mut [Block]) {
+            // encryption and decryption are the same keystream xor
+            self.encrypt(cipher, ciphertext, out);
+        }
+
+        fn keystream_block<C: AesCore>(&self, cipher: &C, index: u64) -> Option<Block> {
+            let mut block = self.counter_block(index);
+            cipher.encrypt_block(&mut block);
+            Some(block)
+        }
+
+        fn is_stream_mode(&self) -> bool {
+            true
+        }
+
+        fn advance(&mut self, ciphertext: &[Block]) {
+            self.initial_counter = self.initial_counter.wrapping_add(ciphertext.len() as u64);
+        }
+    }
+
+    /// AES keyed once and run under any `BlockMode`.
+    /// The key size is picked at runtime by default, use `from_cipher` to fix it statically.
+    pub struct BlockCipher<M: BlockMode, C: AesCore = AnyAes> {
+        cipher: C,
+        mode: M,
+    }
+
+    impl<M: BlockMode> BlockCipher<M> {
+        /// Accepts 16, 24 or 32 byte keys for AES-128, AES-192 and AES-256
+        pub fn new(mode: M, key: &[u8]) -> Result<Self, CryptopalsError> {
+            Ok(BlockCipher::from_cipher(mode, AnyAes::from_key(key)?))
+        }
+    }
+
+    impl<M: BlockMode, C: AesCore> BlockCipher<M, C> {
+        pub fn from_cipher(mode: M, cipher: C) -> Self {
+            BlockCipher { cipher, mode }
+        }
+
+        pub fn mode(&self) -> &M {
+            &self.mode
+        }
+
+        pub fn mode_mut(&mut self) -> &mut M {
+            &mut self.mode
+        }
+
+        /// The raw AES permutation, for callers that want to skip the mode entirely
+        pub fn cipher(&self) -> &C {
+            &self.cipher
+        }
+
+        /// Encrypts a slice of Blocks into a provided output buffer
+        pub fn encrypt(
+            &self,
+            plaintext: &[Block],
+            out: &mut [Block],
+        ) -> Result<(), CryptopalsError> {
+            check_buffer(plaintext, out)?;
+            self.mode.encrypt(&self.cipher, plaintext, out);
+            Ok(())
+        }
+
+        /// Decrypts a slice of Blocks into a provided output buffer
+        pub fn decrypt(
+            &self,
+            ciphertext: &[Block],
+            out: &mut [Block],
+        ) -> Result<(), CryptopalsError> {
+            check_buffer(ciphertext, out)?;
+            self.mode.decrypt(&self.cipher, ciphertext, out);
+            Ok(())
+        }
+
+        /// Encrypts bytes, PKCS#7 padding them first unless the mode is stream-like
+        pub fn encrypt_bytes(&self, plaintext: &[u8]) -> Vec<u8> {
+            if self.mode.is_stream_mode() {
+                return self.encrypt_stream(plaintext).unwrap();
+            }
+
+            let input = bytes_to_blocks(&pkcs_padding(plaintext, BLOCK_SIZE_BYTES));
+            let mut output = vec![Block::default(); input.len()];
+            self.mode.encrypt(&self.cipher, &input, &mut output);
+
+            output.into_iter().flatten().collect()
+        }
+
+        /// Decrypts bytes and strictly strips PKCS#7 padding unless the mode is stream-like
+        pub fn decrypt_bytes(&self, ciphertext: &[u8]) -> Result<Vec<u8>, PaddingError> {
+            if self.mode.is_stream_mode() {
+                return Ok(self.decrypt_stream(ciphertext).unwrap());
+            }
+            if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(BLOCK_SIZE_BYTES) {
+                return Err(PaddingError::InvalidLength(ciphertext.len()));
+            }
+
+            let input = bytes_to_blocks(ciphertext);
+            let mut output = vec![Block::default(); input.len()];
+            self.mode.decrypt(&self.cipher, &input, &mut output);
+
+            let plaintext: Vec<u8> = output.into_iter().flatten().collect();
+            pkcs_unpadding(&plaintext, BLOCK_SIZE_BYTES).map(<[u8]>::to_vec)
+        }
+
+        /// XORs `data` in place with the keystream, starting `offset` bytes into the stream.
+        /// Only works for stream-like modes such as CTR.
+        pub fn apply_keystream(
+            &self,
+            offset: usize,
+            data: &mut [u8],
+        ) -> Result<(), CryptopalsError> {
+            let mut position = offset;
+            let mut data = data;
+
+            while !data.is_empty() {
+                let keystream = self
+                    .mode
+                    .keystream_block(&self.cipher, (position / BLOCK_SIZE_BYTES) as u64)
+                    .ok_or(CryptopalsError::NotAStreamMode)?;
+
+                let start = position % BLOCK_SIZE_BYTES;
+                let len = data.len().min(BLOCK_SIZE_BYTES - start);
+                let (chunk, rest) = data.split_at_mut(len);
+
+                for (byte, key) in chunk.iter_mut().zip(keystream[start..].iter()) {
+                    *byte ^= *key;
+                }
+
+                position += len;
+                data = rest;
+            }
+            Ok(())
+        }
+
+        /// Encrypts arbitrary-length input with a stream-like mode, no padding involved
+        pub fn encrypt_stream(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptopalsError> {
+            let mut ciphertext = plaintext.to_vec();
+            self.apply_keystream(0, &mut ciphertext)?;
+            Ok(ciphertext)
+        }
+
+        /// Decrypts arbitrary-length input with a stream-like mode, no padding involved
+        pub fn decrypt_stream(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptopalsError> {
+            self.encrypt_stream(ciphertext)
+        }
+
+        /// Re-encrypts `newtext` over `ciphertext` starting at byte `offset`, leaving the rest
+        /// untouched. The ciphertext grows if the new text runs past its end.
+        pub fn edit(
+            &self,
+            ciphertext: &[u8],
+            offset: usize,
+            newtext: &[u8],
+        ) -> Result<Vec<u8>, CryptopalsError> {
+            if offset > ciphertext.len() {
+                return Err(CryptopalsError::OffsetOutOfRange {
+                    offset,
+                    len: ciphertext.len(),
+                });
+            }
+
+            let mut encrypted = newtext.to_vec();
+            self.apply_keystream(offset, &mut encrypted)?;
+
+            let end = offset + newtext.len();
+            let mut edited = ciphertext.to_vec();
+            if end > edited.len() {
+                edited.resize(end, 0);
+            }
+            edited[offset..end].copy_from_slice(&encrypted);
+            Ok(edited)
+        }
+    }
+
+    /// Splits block-aligned bytes into Blocks
+    fn bytes_to_blocks(bytes: &[u8]) -> Vec<Block> {
+        bytes
+            .chunks_exact(BLOCK_SIZE_BYTES)
+            .map(Block::clone_from_slice)
+            .collect()
+    }
+
+    fn check_buffer(input: &[Block], out: &[Block]) -> Result<(), CryptopalsError> {
+        if out.len() < input.len() {
+            return Err(CryptopalsError::BufferTooSmall {
+                needed: input.len(),
+                got: out.len(),
+            });
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cbc_round_trip() {
+        use crate::utils::*;
+
+        let plaintext = b"YELLOW SUBMARINE and then some more text to chain".to_vec();
+        let (input, mut ciphertext) = text_to_io(plaintext);
+        let mut decrypted = output_from_block_count(input.len());
+
+        let cipher =
+            BlockCipher::new(Cbc::new(Block::from([7u8; 16])), b"YELLOW SUBMARINE").unwrap();
+        cipher.encrypt(&input, &mut ciphertext).unwrap();
+        cipher.decrypt(&ciphertext, &mut decrypted).unwrap();
+
+        assert_ne!(input, ciphertext);
+        assert_eq!(input, decrypted);
+    }
+
+    #[test]
+    fn rejects_bad_keys_and_buffers() {
+        assert!(matches!(
+            BlockCipher::new(Ecb, b"short"),
+            Err(CryptopalsError::KeyLength(5))
+        ));
+
+        let cipher = BlockCipher::new(Ecb, b"YELLOW SUBMARINE").unwrap();
+        let mut out = [Block::default(); 1];
+        assert!(matches!(
+            cipher.encrypt(&[Block::default(); 2], &mut out),
+            Err(CryptopalsError::BufferTooSmall { needed: 2, got: 1 })
+        ));
+    }
+
+    #[test]
+    fn ctr_decrypts_challenge_18() {
+        let ciphertext = base64::decode(
+            "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+        )
+        .unwrap();
+
+        let cipher = BlockCipher::new(Ctr::cryptopals(0), b"YELLOW SUBMARINE").unwrap();
+        let plaintext = cipher.decrypt_stream(&ciphertext).unwrap();
+
+        assert_eq!(
+            plaintext,
+            b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ".to_vec()
+        );
+        assert_eq!(cipher.encrypt_stream(&plaintext).unwrap(), ciphertext);
+
+        // starting partway into the stream matches the tail of a full decryption
+        let mut tail = ciphertext[21..].to_vec();
+        cipher.apply_keystream(21, &mut tail).unwrap();
+        assert_eq!(tail, plaintext[21..]);
+    }
+
+    #[test]
+    fn ctr_counter_layouts() {
+        let cryptopals = Ctr::cryptopals(1).with_initial_counter(2);
+        let mut expected = [0u8; 16];
+        expected[0] = 1;
+        expected[8] = 3;
+        assert_eq!(cryptopals.counter_block(1), Block::from(expected));
+
+        let gcm = Ctr::gcm([0xaa; 12]).with_initial_counter(u32::MAX as u64);
+        let mut expected = [0xaa; 16];
+        expected[12..].copy_from_slice(&[0, 0, 0, 1]);
+        assert_eq!(gcm.counter_block(2), Block::from(expected));
+
+        let ecb = BlockCipher::new(Ecb, b"YELLOW SUBMARINE").unwrap();
+        assert!(matches!(
+            ecb.encrypt_stream(b"abc"),
+            Err(CryptopalsError::NotAStreamMode)
+        ));
+    }
+
+    #[test]
+    fn key_sizes() {
+        // FIPS-197 appendix C vectors
+        let plaintext =
+            Block::clone_from_slice(&hex::decode("00112233445566778899aabbccddeeff").unwrap());
+        let key = hex::decode("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+            .unwrap();
+
+        for (key_len, expected) in [
+            (16, "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            (24, "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            (32, "8ea2b7ca516745bfeafc49904b496089"),
+        ] {
+            let cipher = BlockCipher::new(Ecb, &key[..key_len]).unwrap();
+            let mut out = [Block::default()];
+            cipher.encrypt(&[plaintext], &mut out).unwrap();
+            assert_eq!(hex::encode(out[0]), expected);
+        }
+
+        assert!(matches!(
+            BlockCipher::new(Ecb, &key[..20]),
+            Err(CryptopalsError::KeyLength(20))
+        ));
+        assert!(Aes256::from_key(&key[..16]).is_err());
+    }
+
+    #[test]
+    fn byte_oriented_round_trip() {
+        let cipher = BlockCipher::new(Cbc::new(Block::default()), b"YELLOW SUBMARINE").unwrap();
+
+        for len in [0, 1, 15, 16, 17, 32] {
+            let plaintext = vec![b'A'; len];
+            let ciphertext = cipher.encrypt_bytes(&plaintext);
+            assert_eq!(ciphertext.len(), (len / 16 + 1) * 16);
+            assert_eq!(cipher.decrypt_bytes(&ciphertext), Ok(plaintext));
+        }
+
+        assert_eq!(
+            cipher.decrypt_bytes(&[0u8; 17]),
+            Err(PaddingError::InvalidLength(17))
+        );
+
+        // stream modes skip padding entirely
+        let ctr = BlockCipher::new(Ctr::cryptopals(0), b"YELLOW SUBMARINE").unwrap();
+        assert_eq!(ctr.encrypt_bytes(b"abc").len(), 3);
+    }
+}
@@ -0,0 +1,207 @@
+// CBC bitflipping (challenge 2.8 / 16): flipping a bit in one ciphertext block flips the
+// same bit in the next plaintext block, which lets us write past an oracle's quoting
+
+pub use cbc_bitflip::*;
+
+#[allow(clippy::module_inception)]
+pub mod cbc_bitflip {
+    use crate::block_cipher::{Block, BLOCK_SIZE_BYTES};
+    use crate::set_two::aes::{CipherMode, AES128};
+    use rand::rngs::OsRng;
+    use rand::{Rng, RngCore};
+    use std::fmt;
+
+    pub const USER_DATA_PREFIX: &[u8] = b"comment1=cooking%20MCs;userdata=";
+    pub const USER_DATA_SUFFIX: &[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+    /// Wraps quoted user data in a cookie and encrypts it with CBC under a random key
+    pub struct UserDataOracle {
+        cipher: AES128,
+    }
+
+    impl UserDataOracle {
+        pub fn new() -> Self {
+            Self::from_rng(&mut OsRng)
+        }
+
+        /// Draws the key and IV from `rng`
+        pub fn from_rng(rng: &mut impl RngCore) -> Self {
+            let key = rng.gen::<[u8; 16]>();
+            let iv = Block::from(rng.gen::<[u8; 16]>());
+
+            UserDataOracle {
+                cipher: AES128::new(CipherMode::CBC(iv), &key),
+            }
+        }
+
+        pub fn encrypt(&self, user_data: &[u8]) -> Vec<u8> {
+            let mut cookie = USER_DATA_PREFIX.to_vec();
+            cookie.extend(quote(user_data));
+            cookie.extend_from_slice(USER_DATA_SUFFIX);
+
+            self.cipher.encrypt_bytes(&cookie)
+        }
+
+        /// Decrypts the cookie and looks for an `admin=true` field
+        pub fn is_admin(&self, ciphertext: &[u8]) -> bool {
+            match self.cipher.decrypt_bytes(ciphertext) {
+                Ok(cookie) => cookie
+                    .split(|byte| *byte == b';')
+                    .any(|field| field == b"admin=true"),
+                Err(_) => false,
+            }
+        }
+    }
+
+    impl Default for UserDataOracle {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// URL-quotes the cookie metacharacters `;` and `=`
+    pub fn quote(input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(input.len());
+        for byte in input {
+            match byte {
+                b';' => output.extend_from_slice(b"%3B"),
+                b'=' => output.extend_from_slice(b"%3D"),
+                _ => output.push(*byte),
+            }
+        }
+        output
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum BitflipError {
+        /// `known` and `target` must be the same length
+        LengthMismatch { known: usize, target: usize },
+        /// The first block can only be changed through the IV
+        FirstBlock,
+        /// The target has to fit inside one block, the block before it gets scrambled
+        SpansBlocks,
+        /// The edit runs past the end of the ciphertext
+        OutOfRange,
+    }
+
+    impl fmt::Display for BitflipError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                BitflipError::LengthMismatch { known, target } => write!(
+                    f,
+                    "known plaintext is {} bytes but target is {}",
+                    known, target
+                ),
+                BitflipError::FirstBlock => write!(f, "can't flip the first block without the IV"),
+                BitflipError::SpansBlocks => write!(f, "target must fit within one block"),
+                BitflipError::OutOfRange => write!(f, "target runs past the end of the ciphertext"),
+            }
+        }
+    }
+
+    impl std::error::Error for BitflipError {}
+
+    /// Computes which ciphertext bytes to xor, and with what, so that the `known` plaintext
+    /// at `plaintext_offset` decrypts to `target` instead.
+    /// Returns `(ciphertext index, xor mask)` pairs, all in the block before the target.
+    pub fn bitflip_deltas(
+        known: &[u8],
+        target: &[u8],
+        plaintext_offset: usize,
+    ) -> Result<Vec<(usize, u8)>, BitflipError> {
+        if known.len() != target.len() {
+            return Err(BitflipError::LengthMismatch {
+                known: known.len(),
+                target: target.len(),
+            });
+        }
+        if plaintext_offset < BLOCK_SIZE_BYTES {
+            return Err(BitflipError::FirstBlock);
+        }
+        if !target.is_empty()
+            && plaintext_offset / BLOCK_SIZE_BYTES
+                != (plaintext_offset + target.len() - 1) / BLOCK_SIZE_BYTES
+        {
+            return Err(BitflipError::SpansBlocks);
+        }
+
+        Ok(known
+            .iter()
+            .zip(target.iter())
+            .enumerate()
+            .filter(|(_, (known, target))| known != target)
+            .map(|(i, (known, target))| (plaintext_offset + i - BLOCK_SIZE_BYTES, known ^ target))
+            .collect())
+    }
+
+    /// Returns a copy of `ciphertext` with `known` at `plaintext_offset` flipped into `target`
+    pub fn inject(
+        ciphertext: &[u8],
+        known: &[u8],
+        target: &[u8],
+        plaintext_offset: usize,
+    ) -> Result<Vec<u8>, BitflipError> {
+        if plaintext_offset + target.len() > ciphertext.len() {
+            return Err(BitflipError::OutOfRange);
+        }
+
+        let mut forged = ciphertext.to_vec();
+        for (index, mask) in bitflip_deltas(known, target, plaintext_offset)? {
+            forged[index] ^= mask;
+        }
+        Ok(forged)
+    }
+
+    /// Produces a ciphertext the oracle accepts as admin, knowing only the cookie layout
+    pub fn make_admin(oracle: &UserDataOracle) -> Vec<u8> {
+        let target = b";admin=true;";
+        // same length as the target with nothing the oracle will quote
+        let known = b"XadminXtrueX";
+
+        // pad user data to a block boundary, then a sacrificial block that gets scrambled
+        let alignment =
+            (BLOCK_SIZE_BYTES - USER_DATA_PREFIX.len() % BLOCK_SIZE_BYTES) % BLOCK_SIZE_BYTES;
+        let mut user_data = vec![b'A'; alignment + BLOCK_SIZE_BYTES];
+        user_data.extend_from_slice(known);
+
+        let ciphertext = oracle.encrypt(&user_data);
+        let offset = USER_DATA_PREFIX.len() + alignment + BLOCK_SIZE_BYTES;
+
+        inject(&ciphertext, known, target, offset).unwrap()
+    }
+
+    #[test]
+    fn bitflip_to_admin() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let oracle = UserDataOracle::from_rng(&mut StdRng::seed_from_u64(16));
+
+        // quoting stops the direct approach
+        assert!(!oracle.is_admin(&oracle.encrypt(b";admin=true;")));
+
+        assert!(oracle.is_admin(&make_admin(&oracle)));
+    }
+
+    #[test]
+    fn deltas_stay_in_previous_block() {
+        assert_eq!(
+            bitflip_deltas(b"a=b", b"a;b", 20),
+            Ok(vec![(5, b'=' ^ b';')])
+        );
+        assert_eq!(
+            bitflip_deltas(b"ab", b"cd", 3),
+            Err(BitflipError::FirstBlock)
+        );
+        assert_eq!(
+            bitflip_deltas(b"ab", b"cd", 31),
+            Err(BitflipError::SpansBlocks)
+        );
+        assert_eq!(
+            bitflip_deltas(b"ab", b"c", 16),
+            Err(BitflipError::LengthMismatch {
+                known: 2,
+                target: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,280 @@
+// Attacks that only need an EncryptionOracle: work out how it encrypts, then what it's hiding
+
+pub use chosen_plaintext::*;
+
+#[allow(clippy::module_inception)]
+pub mod chosen_plaintext {
+    use crate::block_cipher::BLOCK_SIZE_BYTES;
+    use crate::oracle::{EncryptionOracle, RandomEncryptor};
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+    use std::collections::HashSet;
+
+    /// Identical blocks in the probe; enough that at least `PROBE_BLOCKS - 1` stay block aligned
+    /// whatever the oracle puts in front of them
+    const PROBE_BLOCKS: usize = 5;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum EncryptionMode {
+        Ecb,
+        Cbc,
+    }
+
+    /// Number of ciphertext blocks that repeat an earlier block
+    fn repeated_blocks(ciphertext: &[u8]) -> usize {
+        let blocks = ciphertext.chunks_exact(BLOCK_SIZE_BYTES);
+        let total = blocks.len();
+        let distinct = blocks.collect::<HashSet<&[u8]>>().len();
+        total - distinct
+    }
+
+    /// Feeds the oracle a run of identical blocks and guesses ECB if any ciphertext blocks repeat.
+    /// Confidence is the share of the expected repeats that showed up (ECB), or 1.0 when
+    /// nothing repeated at all (CBC).
+    pub fn detect_block_mode(oracle: &mut impl EncryptionOracle) -> (EncryptionMode, f32) {
+        let probe = [0u8; PROBE_BLOCKS * BLOCK_SIZE_BYTES];
+        let repeats = repeated_blocks(&oracle.encrypt(&probe));
+
+        if repeats == 0 {
+            return (EncryptionMode::Cbc, 1.0);
+        }
+
+        // aligned identical blocks minus the first of them
+        let expected = PROBE_BLOCKS - 2;
+        let confidence = (repeats as f32 / expected as f32).min(1.0);
+        (EncryptionMode::Ecb, confidence)
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DetectionReport {
+        pub trials: usize,
+        pub correct: usize,
+        pub mean_confidence: f32,
+    }
+
+    impl DetectionReport {
+        pub fn accuracy(&self) -> f32 {
+            self.correct as f32 / self.trials.max(1) as f32
+        }
+    }
+
+    /// Runs `detect_block_mode` against `trials` fresh `RandomEncryptor`s and checks each
+    /// guess against the mode it really used
+    pub fn measure_detection_accuracy(trials: usize) -> DetectionReport {
+        measure_detection_accuracy_from_rng(trials, &mut OsRng)
+    }
+
+    /// `measure_detection_accuracy` with every oracle's randomness drawn from `rng`
+    pub fn measure_detection_accuracy_from_rng(
+        trials: usize,
+        rng: &mut impl RngCore,
+    ) -> DetectionReport {
+        let mut correct = 0;
+        let mut total_confidence = 0.0f32;
+
+        for _ in 0..trials {
+            let mut oracle = RandomEncryptor::from_rng(&mut *rng);
+            let (mode, confidence) = detect_block_mode(&mut oracle);
+
+            if oracle.last_mode() == Some(mode) {
+                correct += 1;
+            }
+            total_confidence += confidence;
+        }
+
+        DetectionReport {
+            trials,
+            correct,
+            mean_confidence: total_confidence / trials.max(1) as f32,
+        }
+    }
+
+    /// Longest input tried before giving up on seeing the ciphertext grow
+    const MAX_BLOCK_SIZE: usize = 256;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct OracleLayout {
+        pub block_size: usize,
+        /// Bytes the oracle puts in front of our input
+        pub prefix_len: usize,
+        /// Bytes the oracle appends after our input
+        pub suffix_len: usize,
+    }
+
+    /// Grows the input one byte at a time until the ciphertext jumps by a whole block
+    pub fn discover_block_size(oracle: &mut impl EncryptionOracle) -> Option<usize> {
+        let base_len = oracle.encrypt(&[]).len();
+
+        (1..=MAX_BLOCK_SIZE).find_map(|len| {
+            let grown = oracle.encrypt(&vec![0u8; len]).len();
+            (grown > base_len).then(|| grown - base_len)
+        })
+    }
+
+    /// Finds how many bytes an ECB oracle prepends by padding our input until two identical
+    /// blocks of it line up. The input is sent twice with different filler bytes and only a
+    /// pair that repeats both times and changes with the filler counts: blocks the oracle adds
+    /// itself encrypt the same either way, so repeats inside the prefix or suffix are ignored.
+    pub fn discover_prefix_length(
+        oracle: &mut impl EncryptionOracle,
+        block_size: usize,
+    ) -> Option<usize> {
+        (0..block_size).find_map(|padding| {
+            let zeros = oracle.encrypt(&vec![0x00; 2 * block_size + padding]);
+            let ones = oracle.encrypt(&vec![0xff; 2 * block_size + padding]);
+            let zeros = zeros.chunks_exact(block_size).collect::<Vec<&[u8]>>();
+            let ones = ones.chunks_exact(block_size).collect::<Vec<&[u8]>>();
+
+            (0..zeros.len().min(ones.len()).saturating_sub(1))
+                .find(|index| {
+                    zeros[*index] == zeros[*index + 1]
+                        && ones[*index] == ones[*index + 1]
+                        && zeros[*index] != ones[*index]
+                })
+                .and_then(|index| (index * block_size).checked_sub(padding))
+        })
+    }
+
+    /// Finds how many bytes the oracle appends, given what it prepends: the ciphertext grows
+    /// exactly when prefix + input + suffix fills the last block
+    pub fn discover_suffix_length(
+        oracle: &mut impl EncryptionOracle,
+        block_size: usize,
+        prefix_len: usize,
+    ) -> Option<usize> {
+        let base_len = oracle.encrypt(&[]).len();
+
+        (1..=block_size)
+            .find(|len| oracle.encrypt(&vec![0u8; *len]).len() > base_len)
+            .and_then(|len| base_len.checked_sub(prefix_len + len))
+    }
+
+    /// Block size, prefix length and suffix length of an ECB oracle
+    pub fn discover_layout(oracle: &mut impl EncryptionOracle) -> Option<OracleLayout> {
+        let block_size = discover_block_size(oracle)?;
+        let prefix_len = discover_prefix_length(oracle, block_size)?;
+        let suffix_len = discover_suffix_length(oracle, block_size, prefix_len)?;
+
+        Some(OracleLayout {
+            block_size,
+            prefix_len,
+            suffix_len,
+        })
+    }
+
+    /// Byte-at-a-time ECB decryption: recovers whatever the oracle appends to our input.
+    /// Handles any prefix the oracle puts in front and tries every byte value, so binary
+    /// suffixes work too. Returns `None` if the oracle doesn't behave like ECB.
+    pub fn recover_ecb_suffix(oracle: &mut impl EncryptionOracle) -> Option<Vec<u8>> {
+        let layout = discover_layout(oracle)?;
+        let block_size = layout.block_size;
+
+        // filler that pushes our input onto a block boundary past the prefix
+        let alignment = (block_size - layout.prefix_len % block_size) % block_size;
+        let first_block = (layout.prefix_len + alignment) / block_size;
+        let block_at = |ciphertext: &[u8], index: usize| {
+            ciphertext
+                .get(index * block_size..(index + 1) * block_size)
+                .map(<[u8]>::to_vec)
+        };
+
+        let mut recovered = Vec::with_capacity(layout.suffix_len);
+        while recovered.len() < layout.suffix_len {
+            let position = recovered.len();
+
+            // shift the next unknown byte to the end of a block
+            let shift = block_size - 1 - position % block_size;
+            let mut input = vec![0u8; alignment + shift];
+            let target_block = first_block + position / block_size;
+            let target = block_at(&oracle.encrypt(&input), target_block)?;
+
+            // the block_size - 1 bytes in front of the unknown byte are all known
+            input.drain(..alignment);
+            input.extend_from_slice(&recovered);
+            let known = &input[input.len() - (block_size - 1)..];
+
+            // every candidate block in a single query, one block per byte value
+            let mut probe = vec![0u8; alignment];
+            for byte in 0..=255u8 {
+                probe.extend_from_slice(known);
+                probe.push(byte);
+            }
+            let ciphertext = oracle.encrypt(&probe);
+
+            let byte = (0..=255u8).find(|byte| {
+                block_at(&ciphertext, first_block + *byte as usize).as_ref() == Some(&target)
+            })?;
+            recovered.push(byte);
+        }
+
+        Some(recovered)
+    }
+
+    #[test]
+    fn detects_mode_every_time() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let report = measure_detection_accuracy_from_rng(200, &mut StdRng::seed_from_u64(13));
+        println!("{:?}", report);
+
+        assert_eq!(report.accuracy(), 1.0);
+        assert_eq!(report.mean_confidence, 1.0);
+
+        let empty = measure_detection_accuracy_from_rng(0, &mut StdRng::seed_from_u64(13));
+        assert_eq!(empty.accuracy(), 0.0);
+        assert_eq!(empty.mean_confidence, 0.0);
+    }
+
+    #[test]
+    fn discovers_prefixed_layout() {
+        use crate::block_cipher::{BlockCipher, Ecb};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let key = StdRng::seed_from_u64(3).gen::<[u8; 16]>();
+        let cipher = BlockCipher::new(Ecb, &key).unwrap();
+        // 37 and 48 start with two identical blocks of prefix
+        for prefix_len in [0, 1, 15, 16, 21, 37, 48] {
+            // the prefix ends in 0x00 and the suffix starts with 0xff to trip up both fillers
+            let mut prefix = vec![b'p'; prefix_len];
+            if let Some(last) = prefix.last_mut() {
+                *last = 0;
+            }
+
+            let mut oracle = |plaintext: &[u8]| {
+                let mut input = prefix.clone();
+                input.extend_from_slice(plaintext);
+                input.extend_from_slice(b"\xffthe secret suffix");
+                cipher.encrypt_bytes(&input)
+            };
+
+            assert_eq!(
+                discover_layout(&mut oracle),
+                Some(OracleLayout {
+                    block_size: 16,
+                    prefix_len,
+                    suffix_len: 18,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn recovers_binary_suffix_after_prefix() {
+        use crate::block_cipher::{BlockCipher, Ecb};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let cipher = BlockCipher::new(Ecb, &rng.gen::<[u8; 16]>()).unwrap();
+        let prefix = rng.gen::<[u8; 13]>();
+        let secret = (0..=255u8).rev().collect::<Vec<u8>>();
+
+        let mut oracle = |plaintext: &[u8]| {
+            let mut input = prefix.to_vec();
+            input.extend_from_slice(plaintext);
+            input.extend_from_slice(&secret);
+            cipher.encrypt_bytes(&input)
+        };
+
+        assert_eq!(recover_ecb_suffix(&mut oracle), Some(secret.clone()));
+    }
+}
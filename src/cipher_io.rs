@@ -0,0 +1,317 @@
+// std::io adapters so files can be encrypted without holding the whole thing in memory
+
+pub use cipher_io::*;
+
+#[allow(clippy::module_inception)]
+pub mod cipher_io {
+    use crate::block_cipher::{
+        pkcs_padding, pkcs_unpadding, AesCore, AnyAes, Block, BlockCipher, BlockMode,
+        CryptopalsError, BLOCK_SIZE_BYTES,
+    };
+    use std::io::{self, Read, Write};
+
+    /// How much ciphertext `Decryptor` pulls from its reader at a time
+    const READ_CHUNK_BYTES: usize = 4096;
+
+    fn invalid_data(err: CryptopalsError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+
+    /// Runs every block in `bytes` (which must be block aligned) through the cipher
+    /// and moves the mode past them so the next call chains on
+    fn process_blocks<M: BlockMode, C: AesCore>(
+        cipher: &mut BlockCipher<M, C>,
+        bytes: &[u8],
+        encrypt: bool,
+    ) -> Vec<u8> {
+        let input = bytes
+            .chunks_exact(BLOCK_SIZE_BYTES)
+            .map(Block::clone_from_slice)
+            .collect::<Vec<Block>>();
+        let mut output = vec![Block::default(); input.len()];
+
+        if encrypt {
+            cipher.encrypt(&input, &mut output).unwrap();
+            cipher.mode_mut().advance(&output);
+        } else {
+            cipher.decrypt(&input, &mut output).unwrap();
+            cipher.mode_mut().advance(&input);
+        }
+
+        output.into_iter().flatten().collect()
+    }
+
+    /// Encrypts everything written to it into `inner`.
+    /// Partial blocks are buffered until `finish`, which pads (unless the mode is
+    /// stream-like) and must be called or the tail of the plaintext is lost.
+    /// Ciphertext `inner` fails to take is kept and retried before anything else is encrypted.
+    pub struct Encryptor<W: Write, M: BlockMode, C: AesCore = AnyAes> {
+        cipher: BlockCipher<M, C>,
+        inner: W,
+        pending: Vec<u8>,
+        /// Encrypted but not yet accepted by `inner`
+        unwritten: Vec<u8>,
+    }
+
+    impl<W: Write, M: BlockMode, C: AesCore> Encryptor<W, M, C> {
+        pub fn new(cipher: BlockCipher<M, C>, inner: W) -> Self {
+            Encryptor {
+                cipher,
+                inner,
+                pending: Vec::with_capacity(BLOCK_SIZE_BYTES),
+                unwritten: vec![],
+            }
+        }
+
+        /// Hands `unwritten` to the inner writer, dropping only what it accepted, so a
+        /// failure can be retried without losing or repeating ciphertext
+        fn write_unwritten(&mut self) -> io::Result<()> {
+            while !self.unwritten.is_empty() {
+                match self.inner.write(&self.unwritten) {
+                    Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                    Ok(written) => {
+                        self.unwritten.drain(..written);
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        }
+
+        /// Encrypts the last partial block and hands back the writer
+        pub fn finish(mut self) -> io::Result<W> {
+            let tail = if self.cipher.mode().is_stream_mode() {
+                let mut tail = std::mem::take(&mut self.pending);
+                self.cipher
+                    .apply_keystream(0, &mut tail)
+                    .map_err(invalid_data)?;
+                tail
+            } else {
+                let padded = pkcs_padding(&self.pending, BLOCK_SIZE_BYTES);
+                process_blocks(&mut self.cipher, &padded, true)
+            };
+
+            self.write_unwritten()?;
+            self.inner.write_all(&tail)?;
+            self.inner.flush()?;
+            Ok(self.inner)
+        }
+    }
+
+    impl<W: Write, M: BlockMode, C: AesCore> Write for Encryptor<W, M, C> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            // if what an earlier call left behind still won't go, `buf` isn't taken
+            self.write_unwritten()?;
+            self.pending.extend_from_slice(buf);
+
+            let ready = self.pending.len() - self.pending.len() % BLOCK_SIZE_BYTES;
+            if ready > 0 {
+                self.unwritten = process_blocks(&mut self.cipher, &self.pending[..ready], true);
+                self.pending.drain(..ready);
+                // `buf` is encrypted and kept either way, so a failure here is left
+                // for the next write, flush or finish to report
+                let _ = self.write_unwritten();
+            }
+
+            Ok(buf.len())
+        }
+
+        /// Flushes the inner writer; a buffered partial block stays until `finish`
+        fn flush(&mut self) -> io::Result<()> {
+            self.write_unwritten()?;
+            self.inner.flush()
+        }
+    }
+
+    /// Decrypts everything read from `inner`.
+    /// The last block is held back until the reader ends so the padding can be stripped;
+    /// bad padding or a misaligned ciphertext comes back as `InvalidData`.
+    pub struct Decryptor<R: Read, M: BlockMode, C: AesCore = AnyAes> {
+        cipher: BlockCipher<M, C>,
+        inner: R,
+        pending: Vec<u8>,
+        plaintext: Vec<u8>,
+        position: usize,
+        finished: bool,
+    }
+
+    impl<R: Read, M: BlockMode, C: AesCore> Decryptor<R, M, C> {
+        pub fn new(cipher: BlockCipher<M, C>, inner: R) -> Self {
+            Decryptor {
+                cipher,
+                inner,
+                pending: Vec::with_capacity(READ_CHUNK_BYTES + BLOCK_SIZE_BYTES),
+                plaintext: vec![],
+                position: 0,
+                finished: false,
+            }
+        }
+
+        pub fn into_inner(self) -> R {
+            self.inner
+        }
+
+        /// Reads the next chunk of ciphertext and decrypts whatever is safe to decrypt
+        fn fill(&mut self) -> io::Result<()> {
+            let mut chunk = [0u8; READ_CHUNK_BYTES];
+            let read = self.inner.read(&mut chunk)?;
+            if read == 0 {
+                return self.finish();
+            }
+            self.pending.extend_from_slice(&chunk[..read]);
+
+            let mut ready = self.pending.len() - self.pending.len() % BLOCK_SIZE_BYTES;
+            // the final block might be padding, keep one back until we know
+            if !self.cipher.mode().is_stream_mode() && ready == self.pending.len() {
+                ready = ready.saturating_sub(BLOCK_SIZE_BYTES);
+            }
+
+            if ready > 0 {
+                self.plaintext = process_blocks(&mut self.cipher, &self.pending[..ready], false);
+                self.position = 0;
+                self.pending.drain(..ready);
+            }
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.finished = true;
+            let mut tail = std::mem::take(&mut self.pending);
+
+            if self.cipher.mode().is_stream_mode() {
+                self.cipher
+                    .apply_keystream(0, &mut tail)
+                    .map_err(invalid_data)?;
+                self.plaintext = tail;
+            } else {
+                if tail.len() != BLOCK_SIZE_BYTES {
+                    return Err(invalid_data(CryptopalsError::BlockAlignment {
+                        len: tail.len(),
+                        block_size: BLOCK_SIZE_BYTES,
+                    }));
+                }
+                let decrypted = process_blocks(&mut self.cipher, &tail, false);
+                self.plaintext = pkcs_unpadding(&decrypted, BLOCK_SIZE_BYTES)
+                    .map_err(|err| invalid_data(err.into()))?
+                    .to_vec();
+            }
+
+            self.position = 0;
+            Ok(())
+        }
+    }
+
+    impl<R: Read, M: BlockMode, C: AesCore> Read for Decryptor<R, M, C> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            while self.position == self.plaintext.len() && !self.finished {
+                self.fill()?;
+            }
+
+            let available = &self.plaintext[self.position..];
+            let len = available.len().min(buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            self.position += len;
+
+            Ok(len)
+        }
+    }
+
+    #[cfg(test)]
+    fn streamed_round_trip<M: BlockMode + Clone>(mode: M) {
+        let key = b"YELLOW SUBMARINE";
+        let plaintext = (0..10_000u32).map(|i| (i * 7) as u8).collect::<Vec<u8>>();
+
+        // write in awkward sizes so partial blocks get carried between writes
+        let mut encryptor = Encryptor::new(BlockCipher::new(mode.clone(), key).unwrap(), vec![]);
+        for chunk in plaintext.chunks(37) {
+            encryptor.write_all(chunk).unwrap();
+        }
+        let ciphertext = encryptor.finish().unwrap();
+
+        let one_shot = BlockCipher::new(mode.clone(), key).unwrap();
+        assert_eq!(ciphertext, one_shot.encrypt_bytes(&plaintext));
+
+        let mut decrypted = vec![];
+        Decryptor::new(BlockCipher::new(mode, key).unwrap(), ciphertext.as_slice())
+            .read_to_end(&mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn cbc_streams_match_one_shot() {
+        use crate::block_cipher::Cbc;
+        streamed_round_trip(Cbc::new(Block::from([5u8; 16])));
+    }
+
+    #[test]
+    fn ctr_streams_match_one_shot() {
+        use crate::block_cipher::Ctr;
+        streamed_round_trip(Ctr::cryptopals(42));
+    }
+
+    #[test]
+    fn failed_writes_retry_without_duplicating() {
+        use crate::block_cipher::Cbc;
+
+        /// Refuses the first `failures` writes, then behaves like a `Vec`
+        struct FlakyWriter {
+            failures: usize,
+            written: Vec<u8>,
+        }
+
+        impl Write for FlakyWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.failures > 0 {
+                    self.failures -= 1;
+                    return Err(io::ErrorKind::Other.into());
+                }
+                self.written.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mode = Cbc::new(Block::from([5u8; 16]));
+        let key = b"YELLOW SUBMARINE";
+        let flaky = FlakyWriter {
+            failures: 2,
+            written: vec![],
+        };
+        let mut encryptor = Encryptor::new(BlockCipher::new(mode, key).unwrap(), flaky);
+
+        // the first failure is absorbed, the second is reported before the input is taken
+        assert_eq!(encryptor.write(b"first sixteen by").unwrap(), 16);
+        assert!(encryptor.write(b"second sixteen b").is_err());
+        assert_eq!(encryptor.write(b"second sixteen b").unwrap(), 16);
+        let ciphertext = encryptor.finish().unwrap().written;
+
+        let one_shot = BlockCipher::new(mode, key).unwrap();
+        assert_eq!(
+            ciphertext,
+            one_shot.encrypt_bytes(b"first sixteen bysecond sixteen b")
+        );
+    }
+
+    #[test]
+    fn bad_padding_is_invalid_data() {
+        use crate::block_cipher::Ecb;
+
+        let mut ciphertext = BlockCipher::new(Ecb, b"YELLOW SUBMARINE")
+            .unwrap()
+            .encrypt_bytes(b"some secret");
+        ciphertext[15] ^= 1;
+
+        let mut decrypted = vec![];
+        let err = Decryptor::new(
+            BlockCipher::new(Ecb, b"YELLOW SUBMARINE").unwrap(),
+            ciphertext.as_slice(),
+        )
+        .read_to_end(&mut decrypted)
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
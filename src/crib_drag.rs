@@ -0,0 +1,339 @@
+// Crib dragging: when there's too little ciphertext for statistics, guess a piece of the
+// plaintext, slide it along and keep the key bytes wherever it makes the rest readable
+
+pub use crib_drag::*;
+
+#[allow(clippy::module_inception)]
+pub mod crib_drag {
+    use crate::repeating_key_xor::guess_xord_key_with;
+    use crate::scoring::PlaintextScorer;
+    use std::fmt;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct CribMatch {
+        /// Where in the data the crib was placed
+        pub offset: usize,
+        /// What the crib turned the data into there: the other plaintext for xor'd
+        /// ciphertexts, the key bytes for repeating-key xor
+        pub revealed: Vec<u8>,
+        /// The scorer's score for the text this placement decrypts, lower is better
+        pub score: f32,
+    }
+
+    /// Xors two ciphertexts under the same keystream together, leaving the xor of the
+    /// plaintexts. Stops at the end of the shorter one.
+    pub fn xor_ciphertexts(first: &[u8], second: &[u8]) -> Vec<u8> {
+        first
+            .iter()
+            .zip(second.iter())
+            .map(|(a, b)| a ^ b)
+            .collect()
+    }
+
+    /// Slides `crib` along `data` (usually `xor_ciphertexts` of two messages) and scores what
+    /// it reveals at each offset. Returns every placement, most plausible first.
+    pub fn drag_crib(
+        data: &[u8],
+        crib: &[u8],
+        scorer: &(impl PlaintextScorer + ?Sized),
+    ) -> Vec<CribMatch> {
+        if crib.is_empty() || crib.len() > data.len() {
+            return vec![];
+        }
+
+        let matches = (0..=data.len() - crib.len())
+            .map(|offset| {
+                let revealed = xor_ciphertexts(&data[offset..], crib);
+                CribMatch {
+                    offset,
+                    score: scorer.score(&revealed),
+                    revealed,
+                }
+            })
+            .collect();
+
+        best_first(matches)
+    }
+
+    /// Slides `crib` along a repeating-key xor ciphertext. Each placement fixes some key
+    /// bytes, which are used to decrypt the same key positions everywhere else in the
+    /// ciphertext; that text is what gets scored. Placements where a crib longer than the
+    /// key contradicts itself are skipped, as are ones with nothing else to decrypt.
+    pub fn drag_crib_repeating_key(
+        ciphertext: &[u8],
+        key_length: usize,
+        crib: &[u8],
+        scorer: &(impl PlaintextScorer + ?Sized),
+    ) -> Vec<CribMatch> {
+        if crib.is_empty() || crib.len() > ciphertext.len() || key_length == 0 {
+            return vec![];
+        }
+
+        let matches = (0..=ciphertext.len() - crib.len())
+            .filter_map(|offset| {
+                let mut key = PartialKey::new(key_length);
+                key.pin_crib(ciphertext, offset, crib).ok()?;
+
+                // decrypt the pinned positions outside the crib, one run per key period
+                let (mut total, mut weight) = (0f32, 0usize);
+                for (period, chunk) in ciphertext.chunks(key_length).enumerate() {
+                    let start = period * key_length;
+                    let segment = chunk
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| !(offset..offset + crib.len()).contains(&(start + i)))
+                        .filter_map(|(i, byte)| key.get(start + i).map(|k| byte ^ k))
+                        .collect::<Vec<u8>>();
+
+                    if !segment.is_empty() {
+                        total += scorer.score(&segment) * segment.len() as f32;
+                        weight += segment.len();
+                    }
+                }
+                if weight == 0 {
+                    return None;
+                }
+
+                let revealed = (offset..offset + crib.len().min(key_length))
+                    .map(|position| key.get(position).unwrap())
+                    .collect();
+
+                Some(CribMatch {
+                    offset,
+                    revealed,
+                    score: total / weight as f32,
+                })
+            })
+            .collect();
+
+        best_first(matches)
+    }
+
+    fn best_first(mut matches: Vec<CribMatch>) -> Vec<CribMatch> {
+        matches.sort_by(|a, b| a.score.total_cmp(&b.score).then(a.offset.cmp(&b.offset)));
+        matches
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum CribError {
+        /// The position already has a different key byte pinned
+        Conflict {
+            position: usize,
+            pinned: u8,
+            proposed: u8,
+        },
+        /// The crib runs past the end of the ciphertext
+        OutOfRange { offset: usize, len: usize },
+    }
+
+    impl fmt::Display for CribError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CribError::Conflict {
+                    position,
+                    pinned,
+                    proposed,
+                } => write!(
+                    f,
+                    "key position {} is pinned to {:#04x}, not {:#04x}",
+                    position, pinned, proposed
+                ),
+                CribError::OutOfRange { offset, len } => write!(
+                    f,
+                    "crib at offset {} runs past the end of a {} byte ciphertext",
+                    offset, len
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for CribError {}
+
+    /// A key being recovered by hand: positions are pinned one crib at a time and the rest
+    /// can be filled in by statistics. Use the message length as the key length for a
+    /// keystream that never repeats.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PartialKey {
+        bytes: Vec<Option<u8>>,
+    }
+
+    impl PartialKey {
+        /// Panics if `key_length` is 0
+        pub fn new(key_length: usize) -> Self {
+            assert!(key_length > 0, "a key needs at least one byte");
+            PartialKey {
+                bytes: vec![None; key_length],
+            }
+        }
+
+        pub fn key_length(&self) -> usize {
+            self.bytes.len()
+        }
+
+        /// The key byte used at ciphertext `offset`, if it's pinned
+        pub fn get(&self, offset: usize) -> Option<u8> {
+            self.bytes[offset % self.bytes.len()]
+        }
+
+        /// Pins the key byte used at ciphertext `offset`
+        pub fn pin(&mut self, offset: usize, byte: u8) -> Result<(), CribError> {
+            let position = offset % self.bytes.len();
+            match self.bytes[position] {
+                Some(pinned) if pinned != byte => Err(CribError::Conflict {
+                    position,
+                    pinned,
+                    proposed: byte,
+                }),
+                _ => {
+                    self.bytes[position] = Some(byte);
+                    Ok(())
+                }
+            }
+        }
+
+        pub fn unpin(&mut self, offset: usize) {
+            let position = offset % self.bytes.len();
+            self.bytes[position] = None;
+        }
+
+        /// Pins the key bytes that turn `ciphertext[offset..]` into `crib`.
+        /// Nothing is pinned if any of them conflicts with what's already there.
+        pub fn pin_crib(
+            &mut self,
+            ciphertext: &[u8],
+            offset: usize,
+            crib: &[u8],
+        ) -> Result<(), CribError> {
+            if offset + crib.len() > ciphertext.len() {
+                return Err(CribError::OutOfRange {
+                    offset,
+                    len: ciphertext.len(),
+                });
+            }
+
+            let mut pinned = self.clone();
+            for (i, (c, p)) in ciphertext[offset..].iter().zip(crib).enumerate() {
+                pinned.pin(offset + i, c ^ p)?;
+            }
+            *self = pinned;
+            Ok(())
+        }
+
+        /// Decrypts what the pinned bytes allow, putting `placeholder` everywhere else
+        pub fn decrypt(&self, ciphertext: &[u8], placeholder: u8) -> Vec<u8> {
+            ciphertext
+                .iter()
+                .enumerate()
+                .map(|(offset, byte)| self.get(offset).map_or(placeholder, |key| byte ^ key))
+                .collect()
+        }
+
+        /// The full key, with every unpinned position solved as single-byte xor by `scorer`
+        pub fn complete(
+            &self,
+            ciphertext: &[u8],
+            scorer: &(impl PlaintextScorer + ?Sized),
+        ) -> Vec<u8> {
+            self.bytes
+                .iter()
+                .enumerate()
+                .map(|(position, pinned)| {
+                    pinned.unwrap_or_else(|| {
+                        let column = ciphertext
+                            .iter()
+                            .skip(position)
+                            .step_by(self.bytes.len())
+                            .copied()
+                            .collect::<Vec<u8>>();
+                        guess_xord_key_with(&column, scorer)
+                    })
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn drags_across_two_time_pad() {
+        use crate::scoring::LogLikelihood;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let first = b"Meet me at the old bridge tonight";
+        let second = b"Bring the money and come alone!!!";
+        let mut rng = StdRng::seed_from_u64(25);
+        let keystream = (0..first.len())
+            .map(|_| rng.gen::<u8>())
+            .collect::<Vec<u8>>();
+        let c1 = xor_ciphertexts(first, &keystream);
+        let c2 = xor_ciphertexts(second, &keystream);
+
+        let matches = drag_crib(
+            &xor_ciphertexts(&c1, &c2),
+            b" the ",
+            &LogLikelihood::default(),
+        );
+        // " the " sits in the second message, so the first one shows through
+        let best = &matches[0];
+        assert_eq!(best.offset, 5);
+        assert_eq!(best.revealed, b"me at");
+
+        // pinning the crib recovers that part of the keystream for both messages
+        let mut keystream = PartialKey::new(c2.len());
+        keystream.pin_crib(&c2, best.offset, b" the ").unwrap();
+        assert_eq!(&keystream.decrypt(&c1, b'?')[..12], b"?????me at??");
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one byte")]
+    fn empty_key_is_rejected() {
+        PartialKey::new(0);
+    }
+
+    #[test]
+    fn pins_key_bytes_on_short_repeating_key() {
+        use crate::repeating_key_xor::brute_force_ciphertext;
+        use crate::scoring::LetterFrequency;
+        use crate::utils::parse_file_base64;
+
+        // about three key periods: too little for frequency analysis on its own
+        let ciphertext = &parse_file_base64("src/set_one/1-6.txt")[..90];
+        let key = b"Terminator X: Bring the noise";
+        let (_, greedy_key) = brute_force_ciphertext(ciphertext, key.len());
+        assert_ne!(greedy_key, key);
+
+        let matches = drag_crib_repeating_key(
+            ciphertext,
+            key.len(),
+            b"I'm back and I'm ringin' the bell",
+            &LetterFrequency::default(),
+        );
+        assert_eq!(matches[0].offset, 0);
+        assert_eq!(matches[0].revealed, key);
+
+        let mut partial = PartialKey::new(key.len());
+        partial.pin_crib(ciphertext, 0, b"I'm back").unwrap();
+        assert!(matches!(
+            partial.pin(1, 0),
+            Err(CribError::Conflict { position: 1, .. })
+        ));
+        assert_eq!(
+            partial.pin_crib(ciphertext, 85, b"too long"),
+            Err(CribError::OutOfRange {
+                offset: 85,
+                len: 90
+            })
+        );
+        assert_eq!(&partial.decrypt(ciphertext, b'?')[..10], b"I'm back??");
+
+        // statistics fill in whatever isn't pinned
+        let completed = partial.complete(ciphertext, &LetterFrequency::default());
+        assert_eq!(completed[..8], key[..8]);
+
+        for (i, byte) in matches[0].revealed.iter().enumerate() {
+            partial.pin(matches[0].offset + i, *byte).unwrap();
+        }
+        assert_eq!(
+            partial.complete(ciphertext, &LetterFrequency::default()),
+            key
+        );
+    }
+}
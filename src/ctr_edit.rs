@@ -0,0 +1,95 @@
+// Random access read/write CTR (challenge 4.1 / 25): an edit function that re-encrypts at any
+// offset hands the keystream to anyone who can call it
+
+pub use ctr_edit::*;
+
+#[allow(clippy::module_inception)]
+pub mod ctr_edit {
+    use crate::block_cipher::{BlockCipher, CryptopalsError, Ctr};
+    use rand::rngs::OsRng;
+    use rand::{Rng, RngCore};
+
+    /// Holds a CTR encrypted secret, attackers only get the ciphertext and `edit`
+    pub struct EditOracle {
+        cipher: BlockCipher<Ctr>,
+        ciphertext: Vec<u8>,
+    }
+
+    impl EditOracle {
+        /// Encrypts `plaintext` under a random key and nonce
+        pub fn new(plaintext: &[u8]) -> Self {
+            Self::from_rng(plaintext, &mut OsRng)
+        }
+
+        /// Draws the key and nonce from `rng`
+        pub fn from_rng(plaintext: &[u8], rng: &mut impl RngCore) -> Self {
+            let nonce = rng.gen::<u64>();
+            let cipher = BlockCipher::new(Ctr::cryptopals(nonce), &rng.gen::<[u8; 16]>()).unwrap();
+            let ciphertext = cipher.encrypt_stream(plaintext).unwrap();
+
+            EditOracle { cipher, ciphertext }
+        }
+
+        pub fn ciphertext(&self) -> &[u8] {
+            &self.ciphertext
+        }
+
+        /// Returns the ciphertext with `newtext` encrypted in at `offset`, the stored one is unchanged
+        pub fn edit(&self, offset: usize, newtext: &[u8]) -> Result<Vec<u8>, CryptopalsError> {
+            self.cipher.edit(&self.ciphertext, offset, newtext)
+        }
+    }
+
+    /// Editing the whole ciphertext to zeros returns the raw keystream, xor it with the
+    /// original ciphertext to get the plaintext back
+    pub fn recover_plaintext(oracle: &EditOracle) -> Vec<u8> {
+        let ciphertext = oracle.ciphertext();
+        let keystream = oracle
+            .edit(0, &vec![0u8; ciphertext.len()])
+            .expect("offset 0 is always in range");
+
+        ciphertext
+            .iter()
+            .zip(keystream.iter())
+            .map(|(c, k)| c ^ k)
+            .collect()
+    }
+
+    #[test]
+    fn edit_only_touches_the_range() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let oracle = EditOracle::from_rng(
+            b"Yo, VIP Let's kick it Ice, Ice, baby",
+            &mut StdRng::seed_from_u64(25),
+        );
+        let original = oracle.ciphertext().to_vec();
+
+        let edited = oracle.edit(8, b"Lets").unwrap();
+        assert_eq!(edited.len(), original.len());
+        assert_eq!(edited[..8], original[..8]);
+        assert_eq!(edited[12..], original[12..]);
+        assert_ne!(edited[8..12], original[8..12]);
+
+        // writing past the end extends the ciphertext
+        assert_eq!(
+            oracle.edit(original.len(), b"!!").unwrap().len(),
+            original.len() + 2
+        );
+        assert!(oracle.edit(original.len() + 1, b"!!").is_err());
+    }
+
+    /// 4.1
+    #[test]
+    fn recovers_1_7_plaintext() {
+        use crate::utils::lyrics;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let plaintext = lyrics();
+
+        let oracle = EditOracle::from_rng(&plaintext, &mut StdRng::seed_from_u64(25));
+        let recovered = recover_plaintext(&oracle);
+
+        assert_eq!(recovered, plaintext);
+    }
+}
@@ -0,0 +1,215 @@
+// ECB cut-and-paste (challenge 2.5 / 13): ECB encrypts every block on its own, so blocks from
+// different ciphertexts can be spliced together into a cookie nobody ever encrypted
+
+pub use ecb_cut_paste::*;
+
+#[allow(clippy::module_inception)]
+pub mod ecb_cut_paste {
+    use crate::block_cipher::{pkcs_padding, PaddingError, BLOCK_SIZE_BYTES};
+    use crate::set_two::aes::{CipherMode, AES128};
+    use rand::rngs::OsRng;
+    use rand::{Rng, RngCore};
+    use std::fmt;
+
+    /// Everything `profile_for` puts in front of the email
+    const EMAIL_PREFIX: &str = "email=";
+    /// Everything between the email and the role's value
+    const ROLE_PREFIX: &str = "&uid=10&role=";
+    /// Where the forged profile's email lives
+    const EMAIL_DOMAIN: &str = "@bar.com";
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum CookieError {
+        /// `&&`, a leading `&` or a trailing `&`
+        EmptyField,
+        /// A field with no `=` in it
+        MissingEquals(String),
+        /// A field starting with `=`
+        EmptyKey(String),
+        /// Decrypted cookie isn't valid utf-8
+        NotUtf8,
+        /// Decrypted cookie isn't validly padded
+        Padding(PaddingError),
+    }
+
+    impl fmt::Display for CookieError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CookieError::EmptyField => write!(f, "empty field"),
+                CookieError::MissingEquals(field) => write!(f, "field {:?} has no '='", field),
+                CookieError::EmptyKey(field) => write!(f, "field {:?} has an empty key", field),
+                CookieError::NotUtf8 => write!(f, "cookie is not valid utf-8"),
+                CookieError::Padding(err) => write!(f, "{}", err),
+            }
+        }
+    }
+
+    impl std::error::Error for CookieError {}
+
+    impl From<PaddingError> for CookieError {
+        fn from(err: PaddingError) -> Self {
+            CookieError::Padding(err)
+        }
+    }
+
+    /// Parses `k=v&k=v` into its pairs, in order. Values may be empty and may contain `=`,
+    /// everything after the first `=` of a field is the value.
+    pub fn parse_kv(input: &str) -> Result<Vec<(String, String)>, CookieError> {
+        if input.is_empty() {
+            return Ok(vec![]);
+        }
+
+        input
+            .split('&')
+            .map(|field| {
+                if field.is_empty() {
+                    return Err(CookieError::EmptyField);
+                }
+                match field.split_once('=') {
+                    None => Err(CookieError::MissingEquals(field.to_string())),
+                    Some(("", _)) => Err(CookieError::EmptyKey(field.to_string())),
+                    Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                }
+            })
+            .collect()
+    }
+
+    /// The inverse of `parse_kv`. Doesn't escape anything, callers strip metacharacters.
+    pub fn encode_kv(pairs: &[(String, String)]) -> String {
+        pairs
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// Encodes a user profile, dropping `&` and `=` so the email can't add fields
+    pub fn profile_for(email: &str) -> String {
+        let email = email.replace(['&', '='], "");
+
+        encode_kv(&[
+            ("email".to_string(), email),
+            ("uid".to_string(), "10".to_string()),
+            ("role".to_string(), "user".to_string()),
+        ])
+    }
+
+    /// Hands out encrypted profiles under a random ECB key and reads them back
+    pub struct ProfileOracle {
+        cipher: AES128,
+    }
+
+    impl ProfileOracle {
+        pub fn new() -> Self {
+            Self::from_rng(&mut OsRng)
+        }
+
+        /// Draws the key from `rng`
+        pub fn from_rng(rng: &mut impl RngCore) -> Self {
+            ProfileOracle {
+                cipher: AES128::new(CipherMode::ECB, &rng.gen::<[u8; 16]>()),
+            }
+        }
+
+        pub fn encrypt_profile(&self, email: &str) -> Vec<u8> {
+            self.cipher.encrypt_bytes(profile_for(email).as_bytes())
+        }
+
+        pub fn decrypt_profile(
+            &self,
+            ciphertext: &[u8],
+        ) -> Result<Vec<(String, String)>, CookieError> {
+            let plaintext = self.cipher.decrypt_bytes(ciphertext)?;
+            let cookie = String::from_utf8(plaintext).map_err(|_| CookieError::NotUtf8)?;
+
+            parse_kv(&cookie)
+        }
+    }
+
+    impl Default for ProfileOracle {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Builds a ciphertext that decrypts to a profile with `role=admin`, using only
+    /// `encrypt_profile` and the layout of the cookie
+    pub fn make_admin_profile(oracle: &ProfileOracle) -> Vec<u8> {
+        // an email that starts a fresh block with "admin" plus its padding, which
+        // survives profile_for since the padding bytes aren't metacharacters
+        let alignment =
+            (BLOCK_SIZE_BYTES - EMAIL_PREFIX.len() % BLOCK_SIZE_BYTES) % BLOCK_SIZE_BYTES;
+        let admin_block = pkcs_padding(b"admin", BLOCK_SIZE_BYTES);
+        let mut email = "A".repeat(alignment);
+        email.push_str(std::str::from_utf8(&admin_block).unwrap());
+
+        let offset = EMAIL_PREFIX.len() + alignment;
+        let ciphertext = oracle.encrypt_profile(&email);
+        let admin = &ciphertext[offset..offset + BLOCK_SIZE_BYTES];
+
+        // an email long enough that "role=" ends a block, so the value starts the next one
+        let head_len = EMAIL_PREFIX.len() + ROLE_PREFIX.len();
+        let mut email_len = (BLOCK_SIZE_BYTES - head_len % BLOCK_SIZE_BYTES) % BLOCK_SIZE_BYTES;
+        // too short to hold the domain, so spend another block on the local part
+        if email_len < EMAIL_DOMAIN.len() {
+            email_len += BLOCK_SIZE_BYTES;
+        }
+        let local_part = "f".repeat(email_len - EMAIL_DOMAIN.len());
+        let email = format!("{}{}", local_part, EMAIL_DOMAIN);
+        let ciphertext = oracle.encrypt_profile(&email);
+
+        let mut forged = ciphertext[..head_len + email.len()].to_vec();
+        forged.extend_from_slice(admin);
+        forged
+    }
+
+    #[test]
+    fn parses_and_encodes() {
+        let pairs = parse_kv("foo=bar&baz=qux&zap=zazzle").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("foo".to_string(), "bar".to_string()),
+                ("baz".to_string(), "qux".to_string()),
+                ("zap".to_string(), "zazzle".to_string()),
+            ]
+        );
+        assert_eq!(encode_kv(&pairs), "foo=bar&baz=qux&zap=zazzle");
+
+        assert_eq!(parse_kv(""), Ok(vec![]));
+        assert_eq!(
+            parse_kv("a=b=c&d="),
+            Ok(vec![
+                ("a".to_string(), "b=c".to_string()),
+                ("d".to_string(), String::new()),
+            ])
+        );
+        assert_eq!(parse_kv("a=b&&c=d"), Err(CookieError::EmptyField));
+        assert_eq!(
+            parse_kv("a=b&cd"),
+            Err(CookieError::MissingEquals("cd".to_string()))
+        );
+        assert_eq!(parse_kv("=b"), Err(CookieError::EmptyKey("=b".to_string())));
+    }
+
+    #[test]
+    fn profile_strips_metacharacters() {
+        assert_eq!(
+            profile_for("foo@bar.com&role=admin"),
+            "email=foo@bar.comroleadmin&uid=10&role=user"
+        );
+    }
+
+    #[test]
+    fn cut_and_paste_admin() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let oracle = ProfileOracle::from_rng(&mut StdRng::seed_from_u64(13));
+        let forged = make_admin_profile(&oracle);
+        let profile = oracle.decrypt_profile(&forged).unwrap();
+
+        println!("{:?}", profile);
+        assert!(profile.contains(&("role".to_string(), "admin".to_string())));
+        assert!(!profile.contains(&("role".to_string(), "user".to_string())));
+    }
+}
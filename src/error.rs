@@ -0,0 +1,128 @@
+// Crate-wide error type so tools can report failures instead of aborting
+
+pub use error::*;
+
+#[allow(clippy::module_inception)]
+pub mod error {
+    use crate::block_cipher::PaddingError;
+    use std::{fmt, io};
+
+    #[derive(Debug)]
+    pub enum CryptopalsError {
+        /// The key isn't a length the cipher accepts
+        KeyLength(usize),
+        /// Input has to be a whole number of blocks
+        BlockAlignment {
+            len: usize,
+            block_size: usize,
+        },
+        /// Decrypted plaintext isn't validly padded
+        Padding(PaddingError),
+        /// Input wasn't valid base64
+        Base64(base64::DecodeError),
+        /// Input wasn't valid hex
+        Hex(hex::FromHexError),
+        Io(io::Error),
+        /// Two inputs that have to line up don't
+        LengthMismatch {
+            left: usize,
+            right: usize,
+        },
+        /// The output buffer has fewer blocks than the input
+        BufferTooSmall {
+            needed: usize,
+            got: usize,
+        },
+        /// Byte-oriented keystream operations need a mode like CTR
+        NotAStreamMode,
+        /// An edit can't start past the end of the ciphertext
+        OffsetOutOfRange {
+            offset: usize,
+            len: usize,
+        },
+        /// The input is too short to produce enough key length candidates
+        NotEnoughCandidates {
+            needed: usize,
+            got: usize,
+        },
+        /// A saved language model couldn't be parsed
+        ModelFormat {
+            line: usize,
+        },
+    }
+
+    impl fmt::Display for CryptopalsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CryptopalsError::KeyLength(len) => write!(f, "invalid key length: {}", len),
+                CryptopalsError::BlockAlignment { len, block_size } => write!(
+                    f,
+                    "length {} is not a multiple of the {} byte block size",
+                    len, block_size
+                ),
+                CryptopalsError::Padding(err) => write!(f, "{}", err),
+                CryptopalsError::Base64(err) => write!(f, "base64: {}", err),
+                CryptopalsError::Hex(err) => write!(f, "hex: {}", err),
+                CryptopalsError::Io(err) => write!(f, "io: {}", err),
+                CryptopalsError::LengthMismatch { left, right } => {
+                    write!(f, "lengths don't match: {} and {}", left, right)
+                }
+                CryptopalsError::BufferTooSmall { needed, got } => write!(
+                    f,
+                    "output buffer too small: needed {} blocks, got {}",
+                    needed, got
+                ),
+                CryptopalsError::NotAStreamMode => write!(f, "mode does not produce a keystream"),
+                CryptopalsError::OffsetOutOfRange { offset, len } => write!(
+                    f,
+                    "offset {} is past the end of a {} byte ciphertext",
+                    offset, len
+                ),
+                CryptopalsError::NotEnoughCandidates { needed, got } => write!(
+                    f,
+                    "needed {} key length candidates, input only allows {}",
+                    needed, got
+                ),
+                CryptopalsError::ModelFormat { line } => {
+                    write!(f, "malformed language model on line {}", line)
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for CryptopalsError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                CryptopalsError::Padding(err) => Some(err),
+                CryptopalsError::Base64(err) => Some(err),
+                CryptopalsError::Hex(err) => Some(err),
+                CryptopalsError::Io(err) => Some(err),
+                _ => None,
+            }
+        }
+    }
+
+    impl From<PaddingError> for CryptopalsError {
+        fn from(err: PaddingError) -> Self {
+            CryptopalsError::Padding(err)
+        }
+    }
+
+    impl From<base64::DecodeError> for CryptopalsError {
+        fn from(err: base64::DecodeError) -> Self {
+            CryptopalsError::Base64(err)
+        }
+    }
+
+    impl From<hex::FromHexError> for CryptopalsError {
+        fn from(err: hex::FromHexError) -> Self {
+            CryptopalsError::Hex(err)
+        }
+    }
+
+    impl From<io::Error> for CryptopalsError {
+        fn from(err: io::Error) -> Self {
+            CryptopalsError::Io(err)
+        }
+    }
+}
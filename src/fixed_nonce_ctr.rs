@@ -0,0 +1,144 @@
+// Fixed-nonce CTR (challenges 3.3 and 3.4 / 19 and 20): every message is xor'd with the same
+// keystream, so lining the ciphertexts up turns it into repeating-key xor
+
+pub use fixed_nonce_ctr::*;
+
+#[allow(clippy::module_inception)]
+pub mod fixed_nonce_ctr {
+    use crate::repeating_key_xor::{guess_xord_key, utils::transpose};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FixedNonceResult {
+        /// The recovered shared keystream
+        pub keystream: Vec<u8>,
+        /// Each ciphertext xor'd with as much of the keystream as was recovered
+        pub plaintexts: Vec<Vec<u8>>,
+    }
+
+    /// Truncates every ciphertext to the shortest one and solves them as repeating-key xor
+    /// with key length = shortest message
+    pub fn break_fixed_nonce_truncated(ciphertexts: &[Vec<u8>]) -> FixedNonceResult {
+        let shortest = ciphertexts.iter().map(Vec::len).min().unwrap_or(0);
+        if shortest == 0 {
+            return recover(ciphertexts, vec![]);
+        }
+
+        let chunks = ciphertexts
+            .iter()
+            .map(|ciphertext| &ciphertext[..shortest])
+            .collect::<Vec<&[u8]>>();
+
+        let keystream = transpose(chunks)
+            .iter()
+            .map(|column| guess_xord_key(column))
+            .collect();
+
+        recover(ciphertexts, keystream)
+    }
+
+    /// Solves every keystream position using all the ciphertexts long enough to reach it,
+    /// so nothing is thrown away when the messages have different lengths.
+    /// Positions only covered by a few messages are less reliable.
+    pub fn break_fixed_nonce(ciphertexts: &[Vec<u8>]) -> FixedNonceResult {
+        let longest = ciphertexts.iter().map(Vec::len).max().unwrap_or(0);
+
+        let keystream = (0..longest)
+            .map(|position| {
+                let column = ciphertexts
+                    .iter()
+                    .filter_map(|ciphertext| ciphertext.get(position).copied())
+                    .collect::<Vec<u8>>();
+                guess_xord_key(&column)
+            })
+            .collect();
+
+        recover(ciphertexts, keystream)
+    }
+
+    fn recover(ciphertexts: &[Vec<u8>], keystream: Vec<u8>) -> FixedNonceResult {
+        let plaintexts = ciphertexts
+            .iter()
+            .map(|ciphertext| {
+                ciphertext
+                    .iter()
+                    .zip(keystream.iter())
+                    .map(|(c, k)| c ^ k)
+                    .collect()
+            })
+            .collect();
+
+        FixedNonceResult {
+            keystream,
+            plaintexts,
+        }
+    }
+
+    #[cfg(test)]
+    fn encrypt_lines_with_fixed_nonce() -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+        use crate::block_cipher::{BlockCipher, Ctr};
+        use crate::utils::lyrics;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let plaintexts = lyrics()
+            .split(|byte| *byte == b'\n')
+            .filter(|line| line.len() > 8)
+            .map(<[u8]>::to_vec)
+            .collect::<Vec<Vec<u8>>>();
+
+        let cipher = BlockCipher::new(
+            Ctr::cryptopals(0),
+            &StdRng::seed_from_u64(19).gen::<[u8; 16]>(),
+        )
+        .unwrap();
+        let ciphertexts = plaintexts
+            .iter()
+            .map(|plaintext| cipher.encrypt_stream(plaintext).unwrap())
+            .collect();
+
+        (plaintexts, ciphertexts)
+    }
+
+    #[cfg(test)]
+    fn matching_bytes(expected: &[Vec<u8>], recovered: &[Vec<u8>]) -> f32 {
+        let (mut matching, mut total) = (0, 0);
+        for (expected, recovered) in expected.iter().zip(recovered) {
+            for (e, r) in expected.iter().zip(recovered) {
+                matching += (e == r) as usize;
+                total += 1;
+            }
+        }
+        matching as f32 / total as f32
+    }
+
+    #[test]
+    fn breaks_truncated() {
+        let (plaintexts, ciphertexts) = encrypt_lines_with_fixed_nonce();
+        let result = break_fixed_nonce_truncated(&ciphertexts);
+
+        for plaintext in result.plaintexts.iter().take(5) {
+            println!("{}", String::from_utf8_lossy(plaintext));
+        }
+        assert!(matching_bytes(&plaintexts, &result.plaintexts) > 0.85);
+    }
+
+    #[test]
+    fn breaks_unequal_lengths() {
+        let (plaintexts, ciphertexts) = encrypt_lines_with_fixed_nonce();
+        let result = break_fixed_nonce(&ciphertexts);
+        let shortest = ciphertexts.iter().map(Vec::len).min().unwrap();
+
+        for plaintext in result.plaintexts.iter().take(5) {
+            println!("{}", String::from_utf8_lossy(plaintext));
+        }
+        assert!(result
+            .plaintexts
+            .iter()
+            .zip(&ciphertexts)
+            .all(|(p, c)| p.len() == c.len()));
+        assert!(result
+            .plaintexts
+            .iter()
+            .any(|plaintext| plaintext.len() > shortest));
+        assert!(matching_bytes(&plaintexts, &result.plaintexts) > 0.9);
+    }
+}
@@ -0,0 +1,336 @@
+// Byte and n-gram statistics of a language, trained from a corpus so scoring isn't tied to
+// English prose
+
+pub use language_model::*;
+
+#[allow(clippy::module_inception)]
+pub mod language_model {
+    use crate::error::CryptopalsError;
+    use std::collections::HashMap;
+    use std::fmt::Write;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::OnceLock;
+
+    /// Byte frequencies of English text, including spaces, newlines and punctuation
+    const ENGLISH_FREQUENCIES: [(u8, f32); 81] = [
+        (32, 0.167_564_44),
+        (101, 0.086_102_29),
+        (116, 0.063_296_5),
+        (97, 0.061_255_4),
+        (110, 0.055_037_037),
+        (105, 0.054_806_262),
+        (111, 0.054_190_442),
+        (115, 0.051_886_5),
+        (114, 0.051_525_03),
+        (108, 0.032_181_926),
+        (100, 0.031_889_48),
+        (104, 0.026_192_373),
+        (99, 0.025_002_688),
+        (10, 0.019_578_06),
+        (117, 0.019_247_776),
+        (109, 0.018_140_173),
+        (112, 0.017_362_094),
+        (102, 0.015_750_347),
+        (103, 0.012_804_66),
+        (46, 0.011_055_184_5),
+        (121, 0.010_893_687),
+        (98, 0.010_346_445),
+        (119, 0.009_565_83),
+        (44, 0.008_634_492),
+        (118, 0.007_819_144),
+        (48, 0.005_918_945_7),
+        (107, 0.004_945_712),
+        (49, 0.004_937_789_4),
+        (83, 0.003_089_691_5),
+        (84, 0.003_070_106_5),
+        (67, 0.002_987_392_7),
+        (50, 0.002_756_238),
+        (56, 0.002_552_781),
+        (53, 0.002_526_921_2),
+        (65, 0.002_477_483),
+        (57, 0.002_442_242_6),
+        (120, 0.002_306_414_5),
+        (51, 0.002_186_558_7),
+        (73, 0.002_091_041_8),
+        (45, 0.002_076_717_3),
+        (54, 0.001_919_909_8),
+        (52, 0.001_838_527_2),
+        (55, 0.001_824_329_5),
+        (77, 0.001_813_491_2),
+        (66, 0.001_738_700_2),
+        (34, 0.001_575_427_7),
+        (39, 0.001_507_862_2),
+        (80, 0.001_389_084),
+        (69, 0.001_293_820_7),
+        (78, 0.001_275_883_5),
+        (70, 0.001_220_297_3),
+        (82, 0.001_103_737_4),
+        (68, 0.001_092_772_3),
+        (85, 0.001_042_637),
+        (113, 0.001_008_537_4),
+        (76, 0.001_004_480_9),
+        (71, 0.000_931_021),
+        (74, 0.000_881_456_13),
+        (72, 0.000_875_244_7),
+        (79, 0.000_821_052_9),
+        (87, 0.000_804_827),
+        (106, 0.000_617_596_03),
+        (122, 0.000_576_270_86),
+        (47, 0.000_519_607_16),
+        (60, 0.000_441_076_64),
+        (62, 0.000_440_442_82),
+        (75, 0.000_380_800_2),
+        (41, 0.000_331_425_46),
+        (40, 0.000_330_791_63),
+        (86, 0.000_255_620_37),
+        (89, 0.000_251_944_2),
+        (58, 0.000_120_362_776),
+        (81, 0.000_100_017_096),
+        (90, 0.000_086_199_776),
+        (88, 0.000_065_727_33),
+        (59, 0.000_007_415_716),
+        (63, 0.000_004_626_899_7),
+        (127, 0.000_003_105_727_3),
+        (94, 0.000_002_218_376_7),
+        (38, 0.000_002_028_23),
+        (43, 0.000_001_521_172_5),
+    ];
+
+    /// First line of a saved model, so a random file isn't mistaken for one
+    const HEADER: &str = "language-model v1";
+
+    /// Unigram frequencies plus an optional n-gram table of log-probabilities
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct LanguageModel {
+        unigrams: [f32; 256],
+        /// Length of the n-grams, 0 when the model only has unigrams
+        order: usize,
+        ngrams: HashMap<Vec<u8>, f32>,
+        /// Log-probability given to n-grams the corpus never had
+        unseen: f32,
+    }
+
+    impl LanguageModel {
+        /// The hardcoded English byte table, unigrams only
+        pub fn english() -> Self {
+            Self::shared_english().clone()
+        }
+
+        /// `english`, built on first use and shared from then on
+        pub fn shared_english() -> &'static Self {
+            static ENGLISH: OnceLock<LanguageModel> = OnceLock::new();
+
+            ENGLISH.get_or_init(|| {
+                let mut unigrams = [0f32; 256];
+                for (byte, frequency) in ENGLISH_FREQUENCIES {
+                    unigrams[byte as usize] = frequency;
+                }
+
+                LanguageModel {
+                    unigrams,
+                    order: 0,
+                    ngrams: HashMap::new(),
+                    unseen: f32::NEG_INFINITY,
+                }
+            })
+        }
+
+        /// Counts every byte and every overlapping `order`-byte n-gram of `corpus`.
+        /// An order of 0 skips the n-grams.
+        pub fn train(corpus: &[u8], order: usize) -> Self {
+            let mut unigrams = [0f32; 256];
+            for byte in corpus {
+                unigrams[*byte as usize] += 1.0;
+            }
+            for frequency in unigrams.iter_mut() {
+                *frequency /= corpus.len().max(1) as f32;
+            }
+
+            let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+            if order > 0 {
+                for ngram in corpus.windows(order) {
+                    *counts.entry(ngram.to_vec()).or_insert(0) += 1;
+                }
+            }
+
+            let total = counts.values().sum::<usize>().max(1) as f32;
+            let ngrams = counts
+                .into_iter()
+                .map(|(ngram, count)| (ngram, (count as f32 / total).ln()))
+                .collect();
+
+            LanguageModel {
+                unigrams,
+                order,
+                ngrams,
+                unseen: (0.01 / total).ln(),
+            }
+        }
+
+        pub fn train_from_file(
+            path: impl AsRef<Path>,
+            order: usize,
+        ) -> Result<Self, CryptopalsError> {
+            Ok(Self::train(&fs::read(path)?, order))
+        }
+
+        /// The same model with ASCII letters case folded: every uppercase byte's share of the
+        /// unigrams and n-grams is added to the lowercase one
+        pub fn case_folded(&self) -> Self {
+            let mut unigrams = [0f32; 256];
+            for (byte, frequency) in self.unigrams.iter().enumerate() {
+                unigrams[(byte as u8).to_ascii_lowercase() as usize] += frequency;
+            }
+
+            let mut ngrams: HashMap<Vec<u8>, f32> = HashMap::new();
+            for (ngram, log_probability) in &self.ngrams {
+                *ngrams.entry(ngram.to_ascii_lowercase()).or_insert(0.0) += log_probability.exp();
+            }
+
+            LanguageModel {
+                unigrams,
+                order: self.order,
+                ngrams: ngrams
+                    .into_iter()
+                    .map(|(ngram, probability)| (ngram, probability.ln()))
+                    .collect(),
+                unseen: self.unseen,
+            }
+        }
+
+        pub fn unigrams(&self) -> &[f32; 256] {
+            &self.unigrams
+        }
+
+        pub fn frequency(&self, byte: u8) -> f32 {
+            self.unigrams[byte as usize]
+        }
+
+        pub fn order(&self) -> usize {
+            self.order
+        }
+
+        /// Log-probability of `ngram`, or the unseen penalty if the corpus never had it
+        pub fn ngram_log_probability(&self, ngram: &[u8]) -> f32 {
+            *self.ngrams.get(ngram).unwrap_or(&self.unseen)
+        }
+
+        pub fn unseen_log_probability(&self) -> f32 {
+            self.unseen
+        }
+
+        /// Plain text, one table entry per line with n-grams in hex
+        pub fn serialize(&self) -> String {
+            let mut output = format!("{}\norder {}\nunseen {}\n", HEADER, self.order, self.unseen);
+
+            for (byte, frequency) in self.unigrams.iter().enumerate() {
+                if *frequency > 0.0 {
+                    writeln!(output, "u {} {}", byte, frequency).unwrap();
+                }
+            }
+
+            // sorted so the same model always saves to the same file
+            let mut ngrams = self.ngrams.iter().collect::<Vec<_>>();
+            ngrams.sort_by(|a, b| a.0.cmp(b.0));
+            for (ngram, log_probability) in ngrams {
+                writeln!(output, "n {} {}", hex::encode(ngram), log_probability).unwrap();
+            }
+
+            output
+        }
+
+        /// Reads back what `serialize` wrote, pointing at the first bad line if there is one
+        pub fn deserialize(input: &str) -> Result<Self, CryptopalsError> {
+            let mut lines = input.lines().enumerate();
+            let bad_line = |index: usize| CryptopalsError::ModelFormat { line: index + 1 };
+
+            match lines.next() {
+                Some((_, HEADER)) => {}
+                _ => return Err(bad_line(0)),
+            }
+
+            let mut model = LanguageModel {
+                unigrams: [0f32; 256],
+                order: 0,
+                ngrams: HashMap::new(),
+                unseen: f32::NEG_INFINITY,
+            };
+
+            for (index, line) in lines {
+                let fields = line.split(' ').collect::<Vec<&str>>();
+                let parsed = match fields.as_slice() {
+                    ["order", order] => order.parse().ok().map(|order| model.order = order),
+                    ["unseen", unseen] => unseen.parse().ok().map(|unseen| model.unseen = unseen),
+                    ["u", byte, frequency] => byte
+                        .parse::<u8>()
+                        .ok()
+                        .zip(frequency.parse().ok())
+                        .map(|(byte, frequency)| model.unigrams[byte as usize] = frequency),
+                    ["n", ngram, log_probability] => hex::decode(ngram)
+                        .ok()
+                        .filter(|ngram| ngram.len() == model.order)
+                        .zip(log_probability.parse().ok())
+                        .map(|(ngram, log_probability)| {
+                            model.ngrams.insert(ngram, log_probability);
+                        }),
+                    [""] => Some(()),
+                    _ => None,
+                };
+                parsed.ok_or_else(|| bad_line(index))?;
+            }
+
+            Ok(model)
+        }
+
+        pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CryptopalsError> {
+            Ok(fs::write(path, self.serialize())?)
+        }
+
+        pub fn load(path: impl AsRef<Path>) -> Result<Self, CryptopalsError> {
+            Self::deserialize(&fs::read_to_string(path)?)
+        }
+    }
+
+    impl Default for LanguageModel {
+        fn default() -> Self {
+            Self::english()
+        }
+    }
+
+    #[test]
+    fn trains_saves_and_loads() {
+        let path = std::env::temp_dir().join(format!("language-model-{}.txt", std::process::id()));
+        let corpus_path = path.with_extension("corpus");
+        fs::write(&corpus_path, b"{\"level\":\"info\",\"msg\":\"started\"}\n").unwrap();
+
+        let model = LanguageModel::train_from_file(&corpus_path, 3).unwrap();
+        assert_eq!(model.order(), 3);
+        assert_eq!(model.frequency(b'"'), 8.0 / 33.0);
+        assert!(model.ngram_log_probability(b"msg") > model.ngram_log_probability(b"xyz"));
+
+        model.save(&path).unwrap();
+        let loaded = LanguageModel::load(&path);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&corpus_path).unwrap();
+        assert_eq!(loaded.unwrap(), model);
+
+        let english = LanguageModel::english();
+        assert_eq!(
+            LanguageModel::deserialize(&english.serialize()).unwrap(),
+            english
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_models() {
+        assert!(matches!(
+            LanguageModel::deserialize("not a model"),
+            Err(CryptopalsError::ModelFormat { line: 1 })
+        ));
+        assert!(matches!(
+            LanguageModel::deserialize("language-model v1\norder 2\nn abcdef -1.5"),
+            Err(CryptopalsError::ModelFormat { line: 3 })
+        ));
+    }
+}
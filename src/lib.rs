@@ -1,4 +1,19 @@
+pub mod block_cipher;
+pub mod cbc_bitflip;
+pub mod chosen_plaintext;
+pub mod cipher_io;
+pub mod crib_drag;
+pub mod ctr_edit;
+pub mod ecb_cut_paste;
+pub mod error;
+pub mod fixed_nonce_ctr;
+pub mod language_model;
+pub mod oracle;
+pub mod padding_oracle;
 pub mod repeating_key_xor;
+pub mod scoring;
 mod set_one;
 mod set_two;
+pub mod single_byte_xor;
+pub mod telemetry;
 pub mod utils;
@@ -0,0 +1,60 @@
+// Chosen-plaintext oracles share one shape so attacks only have to be written once
+
+pub use oracle::*;
+
+#[allow(clippy::module_inception)]
+pub mod oracle {
+    pub use crate::set_two::byte_at_a_time::EncryptUnknownString;
+    pub use crate::set_two::detection_oracle::RandomEncryptor;
+
+    /// Anything that will encrypt attacker-chosen plaintext and hand back the ciphertext
+    pub trait EncryptionOracle {
+        fn encrypt(&mut self, plaintext: &[u8]) -> Vec<u8>;
+    }
+
+    /// Plain closures work as oracles too
+    impl<F> EncryptionOracle for F
+    where
+        F: FnMut(&[u8]) -> Vec<u8>,
+    {
+        fn encrypt(&mut self, plaintext: &[u8]) -> Vec<u8> {
+            self(plaintext)
+        }
+    }
+
+    #[test]
+    fn oracles_share_one_shape() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        fn ciphertext_len(oracle: &mut impl EncryptionOracle, plaintext: &[u8]) -> usize {
+            oracle.encrypt(plaintext).len()
+        }
+
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut unknown_string = EncryptUnknownString::from_rng(&mut rng);
+        let mut closure = |plaintext: &[u8]| plaintext.to_vec();
+
+        assert_eq!(ciphertext_len(&mut unknown_string, &[]) % 16, 0);
+        assert_eq!(
+            ciphertext_len(&mut RandomEncryptor::from_rng(&mut rng), &[0u8; 64]) % 16,
+            0
+        );
+        assert_eq!(ciphertext_len(&mut closure, b"abc"), 3);
+    }
+
+    #[test]
+    fn seeded_oracles_replay() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut first = RandomEncryptor::from_rng(&mut StdRng::seed_from_u64(7));
+        let mut second = RandomEncryptor::from_rng(&mut StdRng::seed_from_u64(7));
+        for _ in 0..4 {
+            assert_eq!(first.encrypt(b"same input"), second.encrypt(b"same input"));
+            assert_eq!(first.last_mode(), second.last_mode());
+        }
+
+        let mut first = EncryptUnknownString::from_rng(&mut StdRng::seed_from_u64(7));
+        let mut second = EncryptUnknownString::from_rng(&mut StdRng::seed_from_u64(7));
+        assert_eq!(first.encrypt(b"abc"), second.encrypt(b"abc"));
+    }
+}
@@ -2,75 +2,356 @@
 
 pub use repeating_key_xor::*;
 
+#[allow(clippy::module_inception)]
 pub mod repeating_key_xor {
+    use crate::error::CryptopalsError;
+    use crate::scoring::{LetterFrequency, PlaintextScorer};
     use std::collections::HashMap;
     use utils::*;
 
-    /// This iterates through the blocks and uses the hamming distance to guess the key length
-    pub fn guess_key_length(ciphertext: &[u8], key_length_upper_bound: u8) -> Vec<u8> {
-        use itertools::Itertools;
-        // create map for key length to hamming distance
-        let mut distance: HashMap<usize, f32> = HashMap::new();
+    /// Which key lengths `try_guess_key_length` tries and how hard it looks at each
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct KeyLengthOptions {
+        /// Shortest key length tried, at least 1
+        pub min_length: usize,
+        /// Longest key length tried, inclusive
+        pub max_length: usize,
+        /// How many of the best lengths to return
+        pub count: usize,
+        /// Caps the chunk pairs compared per length, spread evenly over all of them.
+        /// `None` compares every pair, which is quadratic in the number of chunks; the
+        /// default of `DEFAULT_MAX_PAIRS` keeps long ciphertexts cheap.
+        pub max_pairs: Option<usize>,
+    }
 
-        // iterate through possible key lengths
-        for key_length in 2..key_length_upper_bound as usize {
-            let mut chunks: Vec<Vec<u8>> = ciphertext
-                .chunks(key_length)
-                .into_iter()
-                .map(|x| x.to_vec())
-                .collect();
+    /// Chunk pairs compared per key length unless `KeyLengthOptions` says otherwise
+    pub const DEFAULT_MAX_PAIRS: usize = 1000;
 
-            // make sure chunks are the same size and theres an even count
-            if chunks.last().unwrap().len() != key_length {
-                chunks.pop();
+    impl Default for KeyLengthOptions {
+        fn default() -> Self {
+            KeyLengthOptions {
+                min_length: 1,
+                max_length: 40,
+                count: 3,
+                max_pairs: Some(DEFAULT_MAX_PAIRS),
             }
-            if chunks.len() % 2 != 0 {
-                chunks.pop();
+        }
+    }
+
+    /// This iterates through the blocks and uses the hamming distance to guess the key length.
+    /// Returns the best three lengths from 1 up to and including `key_length_upper_bound`
+    /// with their distances.
+    pub fn guess_key_length(ciphertext: &[u8], key_length_upper_bound: usize) -> Vec<(usize, f32)> {
+        let options = KeyLengthOptions {
+            max_length: key_length_upper_bound,
+            ..KeyLengthOptions::default()
+        };
+        try_guess_key_length(ciphertext, &options).unwrap()
+    }
+
+    /// Scores every key length in the range by the average hamming distance between chunks
+    /// of that length (per byte, lower is better) and returns up to `count` of the best,
+    /// best first. Errors when the ciphertext doesn't have two full chunks of any length.
+    pub fn try_guess_key_length(
+        ciphertext: &[u8],
+        options: &KeyLengthOptions,
+    ) -> Result<Vec<(usize, f32)>, CryptopalsError> {
+        let distances = (options.min_length.max(1)..=options.max_length)
+            .filter_map(|key_length| {
+                average_chunk_distance(ciphertext, key_length, options.max_pairs)
+                    .map(|distance| (key_length, distance))
+            })
+            .collect::<Vec<(usize, f32)>>();
+
+        best_first(distances, options.count, false)
+    }
+
+    /// Mean hamming distance per byte between pairs of full `key_length` chunks,
+    /// `None` if there aren't two full chunks
+    fn average_chunk_distance(
+        ciphertext: &[u8],
+        key_length: usize,
+        max_pairs: Option<usize>,
+    ) -> Option<f32> {
+        let chunks = ciphertext.chunks_exact(key_length).collect::<Vec<&[u8]>>();
+        let total_pairs = chunks.len() * chunks.len().saturating_sub(1) / 2;
+        if total_pairs == 0 {
+            return None;
+        }
+
+        // every pair, or an evenly spaced selection of them
+        let step = match max_pairs {
+            Some(max_pairs) => total_pairs.div_ceil(max_pairs.max(1)),
+            None => 1,
+        };
+
+        let (mut sum, mut compared) = (0f32, 0usize);
+        for (i, j) in sampled_pairs(chunks.len(), step) {
+            sum += hamming_distance(chunks[i], chunks[j]) as f32 / key_length as f32;
+            compared += 1;
+        }
+
+        Some(sum / compared as f32)
+    }
+
+    /// Every `step`th pair `(i, j)` with `i < j < count`, in nested loop order. Jumps straight
+    /// from one sampled pair to the next, so it costs the rows plus the samples, not every pair.
+    fn sampled_pairs(count: usize, step: usize) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        // `next` and `row_start` count pairs in nested loop order
+        let (mut next, mut row_start) = (0, 0);
+
+        for i in 0..count {
+            let row_len = count - i - 1;
+            while next < row_start + row_len {
+                pairs.push((i, i + 1 + next - row_start));
+                next += step;
             }
+            row_start += row_len;
+        }
+        pairs
+    }
+
+    /// Length of the repeated n-grams `KeyLengthEstimator::Kasiski` looks for
+    const KASISKI_NGRAM_LENGTH: usize = 3;
+
+    /// How close (as a share) a divisor's coincidence has to come to a length's for the
+    /// length to count as a multiple of the real one
+    const COINCIDENCE_TOLERANCE: f32 = 0.1;
+
+    /// Ranks key lengths by the average index of coincidence of their columns, highest first.
+    /// Each column is single-byte xor'd plaintext, so at the right length the columns are as
+    /// repetitive as the language (~0.07 for English) instead of ~1/256 for random bytes.
+    /// Multiples of the real length are just as repetitive, so a length is scaled down by
+    /// its shortest divisor whose coincidence is within `COINCIDENCE_TOLERANCE` of its own.
+    pub fn guess_key_length_by_coincidence(
+        ciphertext: &[u8],
+        options: &KeyLengthOptions,
+    ) -> Result<Vec<(usize, f32)>, CryptopalsError> {
+        // indexed by length, divisors below `min_length` included
+        let coincidences = (0..=options.max_length)
+            .map(|key_length| match key_length {
+                0 => None,
+                _ => average_column_coincidence(ciphertext, key_length),
+            })
+            .collect::<Vec<Option<f32>>>();
+
+        let candidates = (options.min_length.max(1)..=options.max_length)
+            .filter_map(|key_length| {
+                let coincidence = coincidences[key_length]?;
+                let shortest = (1..key_length)
+                    .filter(|divisor| key_length % divisor == 0)
+                    .find(|divisor| {
+                        coincidences[*divisor].is_some_and(|other| {
+                            other >= coincidence * (1.0 - COINCIDENCE_TOLERANCE)
+                        })
+                    })
+                    .unwrap_or(key_length);
+
+                Some((
+                    key_length,
+                    coincidence * shortest as f32 / key_length as f32,
+                ))
+            })
+            .collect::<Vec<(usize, f32)>>();
+
+        best_first(candidates, options.count, true)
+    }
+
+    /// Mean index of coincidence over the columns with at least two bytes in them
+    fn average_column_coincidence(ciphertext: &[u8], key_length: usize) -> Option<f32> {
+        let coincidences = (0..key_length)
+            .filter_map(|column| {
+                let mut counts = [0usize; 256];
+                let mut total = 0;
+                for byte in ciphertext.iter().skip(column).step_by(key_length) {
+                    counts[*byte as usize] += 1;
+                    total += 1;
+                }
+                if total < 2 {
+                    return None;
+                }
 
-            let dist_entry = distance.entry(key_length).or_insert(0 as f32);
+                let matching = counts
+                    .iter()
+                    .map(|n| n * n.saturating_sub(1))
+                    .sum::<usize>();
+                Some(matching as f32 / (total * (total - 1)) as f32)
+            })
+            .collect::<Vec<f32>>();
 
-            // calculate hamming distance: ITERTOOLS IS COOL
-            for (chunk1, chunk2) in chunks.iter().tuples() {
-                let hamm_dist = hamming_distance(chunk1, chunk2);
-                // divide by key length to normalize
-                *dist_entry += hamm_dist as f32 / key_length as f32;
+        if coincidences.is_empty() {
+            return None;
+        }
+        Some(coincidences.iter().sum::<f32>() / coincidences.len() as f32)
+    }
+
+    /// Kasiski examination: the same plaintext n-gram lined up with the same part of the key
+    /// encrypts the same way, so gaps between repeated `ngram_length`-grams are mostly
+    /// multiples of the key length. Each length is scored by how far the share of gaps it
+    /// divides is above the 1 in `length` chance would give; highest first. A multiple of
+    /// the real length divides only part of those gaps, and length 1 divides every gap by
+    /// definition so it scores 0, Kasiski has no evidence to offer for it.
+    /// Lengths without two full chunks of ciphertext, or that divide no gap, are left out.
+    pub fn guess_key_length_by_kasiski(
+        ciphertext: &[u8],
+        options: &KeyLengthOptions,
+        ngram_length: usize,
+    ) -> Result<Vec<(usize, f32)>, CryptopalsError> {
+        let mut last_seen: HashMap<&[u8], usize> = HashMap::new();
+        let mut gaps = vec![];
+        for (position, ngram) in ciphertext.windows(ngram_length.max(1)).enumerate() {
+            if let Some(previous) = last_seen.insert(ngram, position) {
+                gaps.push(position - previous);
             }
+        }
 
-            // divide the sum by chunks to normalize
-            *dist_entry /= chunks.len() as f32;
+        if gaps.is_empty() {
+            return Err(CryptopalsError::NotEnoughCandidates { needed: 1, got: 0 });
         }
 
-        // sort and print
-        let mut sorted_normalized_weights = distance
-            .iter()
-            .sorted_by(|a, b| a.1.partial_cmp(b.1).unwrap());
+        let longest = options.max_length.min(ciphertext.len() / 2);
+        let candidates = (options.min_length.max(1)..=longest)
+            .filter_map(|key_length| {
+                let divided = gaps.iter().filter(|gap| *gap % key_length == 0).count();
+                if divided == 0 {
+                    return None;
+                }
+                let share = divided as f32 / gaps.len() as f32;
+                Some((key_length, share - 1.0 / key_length as f32))
+            })
+            .collect();
+
+        best_first(candidates, options.count, true)
+    }
 
-        let mut top_lengths: Vec<u8> = vec![];
-        for _ in 0..3 {
-            top_lengths.push(*sorted_normalized_weights.next().unwrap().0 as u8);
+    /// Averages each length's rank under hamming distance, index of coincidence and Kasiski
+    /// (when the ciphertext has any repeats), scaled so 0 is best and 1 is worst.
+    /// Only lengths at least one of them returned are ranked. Lowest first.
+    pub fn guess_key_length_ensemble(
+        ciphertext: &[u8],
+        options: &KeyLengthOptions,
+    ) -> Result<Vec<(usize, f32)>, CryptopalsError> {
+        let all = KeyLengthOptions {
+            count: usize::MAX,
+            ..options.clone()
+        };
+
+        let mut rankings = vec![
+            try_guess_key_length(ciphertext, &all)?,
+            guess_key_length_by_coincidence(ciphertext, &all)?,
+        ];
+        if let Ok(kasiski) = guess_key_length_by_kasiski(ciphertext, &all, KASISKI_NGRAM_LENGTH) {
+            rankings.push(kasiski);
+        }
+
+        let candidates = (all.min_length.max(1)..=all.max_length)
+            .filter(|key_length| {
+                rankings
+                    .iter()
+                    .any(|ranking| ranking.iter().any(|(length, _)| length == key_length))
+            })
+            .map(|key_length| {
+                let total_rank = rankings
+                    .iter()
+                    .map(|ranking| {
+                        let worst = ranking.len().saturating_sub(1).max(1) as f32;
+                        ranking
+                            .iter()
+                            .position(|(length, _)| *length == key_length)
+                            .map_or(1.0, |rank| rank as f32 / worst)
+                    })
+                    .sum::<f32>();
+                (key_length, total_rank / rankings.len() as f32)
+            })
+            .collect();
+
+        best_first(candidates, options.count, false)
+    }
+
+    /// Sorts candidates best first, breaking ties towards shorter keys, and keeps `count`
+    fn best_first(
+        mut candidates: Vec<(usize, f32)>,
+        count: usize,
+        higher_is_better: bool,
+    ) -> Result<Vec<(usize, f32)>, CryptopalsError> {
+        if candidates.is_empty() {
+            return Err(CryptopalsError::NotEnoughCandidates { needed: 1, got: 0 });
+        }
+
+        candidates.sort_by(|a, b| {
+            let by_score = if higher_is_better {
+                b.1.total_cmp(&a.1)
+            } else {
+                a.1.total_cmp(&b.1)
+            };
+            by_score.then(a.0.cmp(&b.0))
+        });
+        candidates.truncate(count);
+        Ok(candidates)
+    }
+
+    /// Picks which key length estimator to drive `brute_force_ciphertext` with
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum KeyLengthEstimator {
+        HammingDistance,
+        Coincidence,
+        Kasiski,
+        Ensemble,
+    }
+
+    impl KeyLengthEstimator {
+        /// Scored candidates, best first; the scores mean different things per estimator
+        pub fn estimate(
+            &self,
+            ciphertext: &[u8],
+            options: &KeyLengthOptions,
+        ) -> Result<Vec<(usize, f32)>, CryptopalsError> {
+            match self {
+                KeyLengthEstimator::HammingDistance => try_guess_key_length(ciphertext, options),
+                KeyLengthEstimator::Coincidence => {
+                    guess_key_length_by_coincidence(ciphertext, options)
+                }
+                KeyLengthEstimator::Kasiski => {
+                    guess_key_length_by_kasiski(ciphertext, options, KASISKI_NGRAM_LENGTH)
+                }
+                KeyLengthEstimator::Ensemble => guess_key_length_ensemble(ciphertext, options),
+            }
         }
-        top_lengths
     }
 
     /// Brute forces the ciphertext based on the provided ciphertext and guessed key length.
-    /// Returns the most likely plaintext and key based on lowest delta_from_english
-    pub fn brute_force_ciphertext(ciphertext: &[u8], key_length: u8) -> (Vec<u8>, Vec<u8>) {
+    /// Returns the most likely plaintext and key based on letter frequency
+    pub fn brute_force_ciphertext(ciphertext: &[u8], key_length: usize) -> (Vec<u8>, Vec<u8>) {
+        brute_force_ciphertext_with(ciphertext, key_length, &LetterFrequency::default())
+    }
+
+    /// Same as `brute_force_ciphertext`, with each column's key picked by `scorer`.
+    /// A key length of 0, or one without a full chunk of ciphertext, gives an empty key
+    /// and leaves the ciphertext as it is.
+    pub fn brute_force_ciphertext_with(
+        ciphertext: &[u8],
+        key_length: usize,
+        scorer: &(impl PlaintextScorer + ?Sized),
+    ) -> (Vec<u8>, Vec<u8>) {
+        if key_length == 0 || key_length > ciphertext.len() {
+            return (ciphertext.to_vec(), vec![]);
+        }
+
         // split ciphertext into key_length number of columns (each column will be xor'd with the same byte)
         let chunks = ciphertext_to_chunks(ciphertext, key_length);
         let columns: Vec<Vec<u8>> = transpose(chunks.clone());
 
         // solve each column as a single key xor with lowest hamming distance between them
         let potential_key: Vec<u8> = columns
-            
             .iter()
-            .map(|column| guess_xord_key(column.as_slice()))
+            .map(|column| guess_xord_key_with(column.as_slice(), scorer))
             .collect();
 
         // decrypt the ciphertext using the potential key
         let mut potential_plaintext = Vec::with_capacity(ciphertext.len());
         for (index, letter) in ciphertext.iter().enumerate() {
-            potential_plaintext.push(letter ^ potential_key[index % key_length as usize] as u8);
+            potential_plaintext.push(letter ^ potential_key[index % key_length]);
         }
 
         (potential_plaintext, potential_key)
@@ -78,8 +359,19 @@ pub mod repeating_key_xor {
 
     /// Returns the number of bits that are different between two byte slices.
     pub fn hamming_distance(bytes1: &[u8], bytes2: &[u8]) -> usize {
-        assert!(bytes1.len() == bytes2.len());
-        bytes1.iter().zip(bytes2.iter()).fold(0, |acc, (a, b)| {
+        try_hamming_distance(bytes1, bytes2).unwrap()
+    }
+
+    /// Same as `hamming_distance`, but errors on slices of different lengths
+    pub fn try_hamming_distance(bytes1: &[u8], bytes2: &[u8]) -> Result<usize, CryptopalsError> {
+        if bytes1.len() != bytes2.len() {
+            return Err(CryptopalsError::LengthMismatch {
+                left: bytes1.len(),
+                right: bytes2.len(),
+            });
+        }
+
+        Ok(bytes1.iter().zip(bytes2.iter()).fold(0, |acc, (a, b)| {
             let mut distance: usize = 0;
             let mut xor = a ^ b;
             while xor > 0 {
@@ -89,71 +381,152 @@ pub mod repeating_key_xor {
                 xor >>= 1;
             }
             acc + distance
-        })
+        }))
     }
 
     /// Returns the most likely character of a single key xor'd ciphertext based on the frequency of letters
-    fn guess_xord_key(ciphertext: &[u8]) -> u8 {
-        // new map storing char to english word score
-        let mut char_score = HashMap::with_capacity(255);
-        let mut decrypted_ciphertext = HashMap::with_capacity(255);
-
-        // insert char and score distance of unedited ciphertext
-        let mut highest_score_key = 0u8;
-        let mut highest_score = delta_from_english(ciphertext);
-        char_score.insert(highest_score_key, highest_score);
-
-        // insert key and score of ciphertext xor every key 'a' through 'Z'
-        for potential_key in 0..=255_u8 {
-            let potential_plaintext = ciphertext
-                .to_owned()
-                .iter()
-                .map(|letter| (*letter as u8 ^ potential_key))
-                .collect::<Vec<u8>>();
-
-            decrypted_ciphertext.insert(potential_key, potential_plaintext.clone());
-
-            let potential_key_score = delta_from_english(potential_plaintext.as_slice());
-            if potential_key_score < highest_score {
-                highest_score_key = potential_key;
-                highest_score = potential_key_score;
-            }
+    pub fn guess_xord_key(ciphertext: &[u8]) -> u8 {
+        guess_xord_key_with(ciphertext, &LetterFrequency::default())
+    }
 
-            char_score.insert(potential_key, potential_key_score);
-        }
+    /// Returns the single xor key whose plaintext `scorer` likes best, the lowest key on a tie
+    pub fn guess_xord_key_with(ciphertext: &[u8], scorer: &(impl PlaintextScorer + ?Sized)) -> u8 {
+        rank_xord_keys_with(ciphertext, scorer, 1)[0].0
+    }
 
-        highest_score_key
+    /// The `count` single xor keys `scorer` likes best with their scores, best first
+    pub fn rank_xord_keys_with(
+        ciphertext: &[u8],
+        scorer: &(impl PlaintextScorer + ?Sized),
+        count: usize,
+    ) -> Vec<(u8, f32)> {
+        let mut ranked = (0..=255_u8)
+            .map(|potential_key| {
+                let potential_plaintext = ciphertext
+                    .iter()
+                    .map(|letter| *letter ^ potential_key)
+                    .collect::<Vec<u8>>();
+
+                (potential_key, scorer.score(&potential_plaintext))
+            })
+            .collect::<Vec<(u8, f32)>>();
+
+        // stable, so ties keep the lowest key first
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked.truncate(count.max(1));
+        ranked
     }
 
-    /// statistical likelihood that a potential ciphertext is english based on letter frequency;
-    /// lower means higher likelyhood of ciphertext being the plaintext
-    fn delta_from_english(potential_plaintext_column: &[u8]) -> f32 {
-        let mut overall_delta = 0.0f32;
-        let mut char_count = HashMap::with_capacity(26);
+    /// How wide `beam_search_key` searches
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BeamSearchOptions {
+        /// Best bytes kept for every key position
+        pub candidates_per_column: usize,
+        /// Partial keys kept after each position
+        pub beam_width: usize,
+        /// Full keys returned
+        pub results: usize,
+    }
 
-        // get frequency of each letter
-        for letter in potential_plaintext_column {
-            let letter_count = char_count.entry(*letter).or_insert(0f32);
-            *letter_count += 1f32;
+    impl Default for BeamSearchOptions {
+        fn default() -> Self {
+            BeamSearchOptions {
+                candidates_per_column: 3,
+                beam_width: 32,
+                results: 5,
+            }
         }
+    }
 
-        // normalize count
-        for count in char_count.values_mut() {
-            *count /= (potential_plaintext_column.len()) as f32;
+    /// Builds keys one position at a time from each column's best bytes (by `column_scorer`),
+    /// keeping the partial keys whose decrypted text `text_scorer` likes best, so a column
+    /// that frequency analysis gets wrong can be fixed by its neighbours. `text_scorer` should
+    /// look at contiguous text, e.g. an `NgramScorer`.
+    /// Returns `(key, plaintext, score)` ranked by `text_scorer` on the whole plaintext.
+    pub fn beam_search_key(
+        ciphertext: &[u8],
+        key_length: usize,
+        column_scorer: &(impl PlaintextScorer + ?Sized),
+        text_scorer: &(impl PlaintextScorer + ?Sized),
+        options: &BeamSearchOptions,
+    ) -> Vec<(Vec<u8>, Vec<u8>, f32)> {
+        let column_candidates = (0..key_length)
+            .map(|column| {
+                let column = ciphertext
+                    .iter()
+                    .skip(column)
+                    .step_by(key_length)
+                    .copied()
+                    .collect::<Vec<u8>>();
+                rank_xord_keys_with(&column, column_scorer, options.candidates_per_column)
+            })
+            .collect::<Vec<Vec<(u8, f32)>>>();
+
+        // partial keys with the sum of their column scores, which breaks ties in the text score
+        let mut beam: Vec<(Vec<u8>, f32)> = vec![(vec![], 0.0)];
+        for candidates in column_candidates.iter() {
+            let mut extended = beam
+                .iter()
+                .flat_map(|(key, column_total)| {
+                    candidates.iter().map(move |(byte, score)| {
+                        let mut key = key.clone();
+                        key.push(*byte);
+                        (key, column_total + score)
+                    })
+                })
+                .map(|(key, column_total)| {
+                    let text_score = partial_text_score(ciphertext, &key, key_length, text_scorer);
+                    (key, column_total, text_score)
+                })
+                .collect::<Vec<(Vec<u8>, f32, f32)>>();
+
+            extended.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.1.total_cmp(&b.1)));
+            extended.truncate(options.beam_width.max(1));
+            beam = extended
+                .into_iter()
+                .map(|(key, column_total, _)| (key, column_total))
+                .collect();
         }
 
-        // compute and return delta
-        for (letter, frequency) in char_count.drain() {
-            let mut single_char_delta = character_frequency(letter) - frequency;
+        let mut results = beam
+            .into_iter()
+            .map(|(key, _)| {
+                let plaintext = xor_with_key(ciphertext, &key);
+                let score = text_scorer.score(&plaintext);
+                (key, plaintext, score)
+            })
+            .collect::<Vec<(Vec<u8>, Vec<u8>, f32)>>();
+
+        results.sort_by(|a, b| a.2.total_cmp(&b.2));
+        results.truncate(options.results);
+        results
+    }
 
-            if single_char_delta < 0f32 {
-                single_char_delta *= -1f32;
+    /// Scores the parts of every key period a partial key can decrypt, weighted by length
+    fn partial_text_score(
+        ciphertext: &[u8],
+        partial_key: &[u8],
+        key_length: usize,
+        scorer: &(impl PlaintextScorer + ?Sized),
+    ) -> f32 {
+        let (mut total, mut weight) = (0f32, 0usize);
+        for period in ciphertext.chunks(key_length) {
+            let segment = xor_with_key(&period[..period.len().min(partial_key.len())], partial_key);
+            if !segment.is_empty() {
+                total += scorer.score(&segment) * segment.len() as f32;
+                weight += segment.len();
             }
-
-            overall_delta += single_char_delta
         }
 
-        overall_delta
+        total / weight.max(1) as f32
+    }
+
+    fn xor_with_key(ciphertext: &[u8], key: &[u8]) -> Vec<u8> {
+        ciphertext
+            .iter()
+            .zip(key.iter().cycle())
+            .map(|(c, k)| c ^ k)
+            .collect()
     }
 
     #[test]
@@ -165,135 +538,74 @@ pub mod repeating_key_xor {
         let bytes2 = string2.as_bytes();
 
         assert_eq!(hamming_distance(bytes1, bytes2), 37);
+        assert!(try_hamming_distance(bytes1, b"short").is_err());
     }
 
-    /// Returns table of character frequencies for the English language.
-    /// TODO move this into the function its used in
-    fn character_frequency(byte: u8) -> f32 {
-        let char_freq: HashMap<u8, f32> = HashMap::from([
-            (32, 0.167_564_44),
-            (101, 0.086_102_29),
-            (116, 0.063_296_5),
-            (97, 0.061_255_4),
-            (110, 0.055_037_037),
-            (105, 0.054_806_262),
-            (111, 0.054_190_442),
-            (115, 0.051_886_5),
-            (114, 0.051_525_03),
-            (108, 0.032_181_926),
-            (100, 0.031_889_48),
-            (104, 0.026_192_373),
-            (99, 0.025_002_688),
-            (10, 0.019_578_06),
-            (117, 0.019_247_776),
-            (109, 0.018_140_173),
-            (112, 0.017_362_094),
-            (102, 0.015_750_347),
-            (103, 0.012_804_66),
-            (46, 0.011_055_184_5),
-            (121, 0.010_893_687),
-            (98, 0.010_346_445),
-            (119, 0.009_565_83),
-            (44, 0.008_634_492),
-            (118, 0.007_819_144),
-            (48, 0.005_918_945_7),
-            (107, 0.004_945_712),
-            (49, 0.004_937_789_4),
-            (83, 0.003_089_691_5),
-            (84, 0.003_070_106_5),
-            (67, 0.002_987_392_7),
-            (50, 0.002_756_238),
-            (56, 0.002_552_781),
-            (53, 0.002_526_921_2),
-            (65, 0.002_477_483),
-            (57, 0.002_442_242_6),
-            (120, 0.002_306_414_5),
-            (51, 0.002_186_558_7),
-            (73, 0.002_091_041_8),
-            (45, 0.002_076_717_3),
-            (54, 0.001_919_909_8),
-            (52, 0.001_838_527_2),
-            (55, 0.001_824_329_5),
-            (77, 0.001_813_491_2),
-            (66, 0.001_738_700_2),
-            (34, 0.001_575_427_7),
-            (39, 0.001_507_862_2),
-            (80, 0.001_389_084),
-            (69, 0.001_293_820_7),
-            (78, 0.001_275_883_5),
-            (70, 0.001_220_297_3),
-            (82, 0.001_103_737_4),
-            (68, 0.001_092_772_3),
-            (85, 0.001_042_637),
-            (113, 0.001_008_537_4),
-            (76, 0.001_004_480_9),
-            (71, 0.000_931_021),
-            (74, 0.000_881_456_13),
-            (72, 0.000_875_244_7),
-            (79, 0.000_821_052_9),
-            (87, 0.000_804_827),
-            (106, 0.000_617_596_03),
-            (122, 0.000_576_270_86),
-            (47, 0.000_519_607_16),
-            (60, 0.000_441_076_64),
-            (62, 0.000_440_442_82),
-            (75, 0.000_380_800_2),
-            (41, 0.000_331_425_46),
-            (40, 0.000_330_791_63),
-            (86, 0.000_255_620_37),
-            (89, 0.000_251_944_2),
-            (58, 0.000_120_362_776),
-            (81, 0.000_100_017_096),
-            (90, 0.000_086_199_776),
-            (88, 0.000_065_727_33),
-            (59, 0.000_007_415_716),
-            (63, 0.000_004_626_899_7),
-            (127, 0.000_003_105_727_3),
-            (94, 0.000_002_218_376_7),
-            (38, 0.000_002_028_23),
-            (43, 0.000_001_521_172_5),
-        ]);
-
-        *char_freq.get(&byte).unwrap_or(&0f32)
+    #[test]
+    fn short_ciphertext_is_an_error() {
+        let options = KeyLengthOptions::default();
+        assert!(matches!(
+            try_guess_key_length(&[], &options),
+            Err(CryptopalsError::NotEnoughCandidates { needed: 1, got: 0 })
+        ));
+
+        // only lengths 1 and 2 have two full chunks
+        let candidates = try_guess_key_length(&[1, 2, 3, 4, 5], &options).unwrap();
+        assert_eq!(candidates.len(), 2);
     }
 
     #[test]
-    fn test_delta_from_english() {
-        let potential_plaintext = String::from("The very first well-documented description of a polyalphabetic cipher was by Leon Battista Alberti around 1467 and used a metal");
-        println!(
-            "plaintext: {}",
-            delta_from_english(potential_plaintext.as_bytes())
-        );
-
-        let not_likely_plaintext = String::from("Dlc fipi jgbwr gijv-hmmykorroh bowabmndmmx sd k tmvcyvtfkfcdma mmnrip geq lc Josl Lerdmqde Yvfcbxg kvmerb 1467 krb ewcn e koxyv");
-        println!(
-            "not plaintext: {}",
-            delta_from_english(not_likely_plaintext.as_bytes())
-        );
+    fn samples_pairs_evenly() {
+        for (count, step) in [(0, 1), (2, 1), (7, 1), (7, 4), (30, 13), (30, 1000)] {
+            let every_pair = (0..count)
+                .flat_map(|i| (i + 1..count).map(move |j| (i, j)))
+                .step_by(step)
+                .collect::<Vec<(usize, usize)>>();
+            assert_eq!(sampled_pairs(count, step), every_pair, "{} {}", count, step);
+        }
+    }
 
-        let not_plaintext = String::from("Cmm znwg jrwax fjtp-mtkyvjvxni libhzmyyqsw tn e yttcjqxljgmxrh kmymmv ffa fh Qmsw Gixcnaxj Ftfnwbm jwwywi 1467 irm zaim f uicft");
-        println!(
-            "not plaintext: {}",
-            delta_from_english(not_plaintext.as_bytes())
-        );
+    #[test]
+    fn finds_long_keys() {
+        use crate::utils::lyrics;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let plaintext = lyrics();
+        let mut rng = StdRng::seed_from_u64(29);
+        let key = (0..300).map(|_| rng.gen::<u8>()).collect::<Vec<u8>>();
+        let ciphertext = plaintext
+            .iter()
+            .zip(key.iter().cycle())
+            .map(|(p, k)| p ^ k)
+            .collect::<Vec<u8>>();
+
+        let options = KeyLengthOptions {
+            min_length: 200,
+            max_length: 400,
+            count: 5,
+            max_pairs: Some(20),
+        };
+        let candidates = try_guess_key_length(&ciphertext, &options).unwrap();
+        assert_eq!(candidates.len(), 5);
+        assert_eq!(candidates[0].0, 300);
+        assert!(candidates.windows(2).all(|pair| pair[0].1 <= pair[1].1));
     }
+
     pub mod utils {
         /// Splits ciphertext into slices of size `key_length`, leaving the last slice unpadded.
-        pub fn ciphertext_to_chunks(ciphertext: &[u8], key_length: u8) -> Vec<&[u8]> {
-            let mut chunks: Vec<&[u8]> = vec![];
-            for i in 0..(ciphertext.len() / key_length as usize) {
-                chunks
-                    .push(&ciphertext[(i * key_length as usize)..((i + 1) * key_length as usize)]);
-            }
-            chunks
+        pub fn ciphertext_to_chunks(ciphertext: &[u8], key_length: usize) -> Vec<&[u8]> {
+            ciphertext.chunks_exact(key_length).collect()
         }
 
-        /// Transpose Chunks -> .collect_vec() to a Vec of vecs
+        /// Transpose Chunks -> .collect_vec() to a Vec of vecs, empty for no chunks
         pub fn transpose<T>(v: Vec<&[T]>) -> Vec<Vec<T>>
         where
             T: Clone,
         {
-            let mut transposed = vec![vec![]; v[0].len()];
+            let Some(first) = v.first() else {
+                return vec![];
+            };
+            let mut transposed = vec![vec![]; first.len()];
 
             for row in v {
                 for (i, col) in row.iter().enumerate() {
@@ -304,4 +616,155 @@ pub mod repeating_key_xor {
             transposed
         }
     }
+
+    #[test]
+    fn brute_force_with_each_scorer() {
+        use crate::scoring::{ChiSquared, LogLikelihood};
+        use crate::utils::parse_file_base64;
+
+        let ciphertext = parse_file_base64("src/set_one/1-6.txt");
+        let scorers: [&dyn PlaintextScorer; 3] = [
+            &LetterFrequency::default(),
+            &ChiSquared::default(),
+            &LogLikelihood::default(),
+        ];
+
+        for scorer in scorers {
+            let (_, key) = brute_force_ciphertext_with(&ciphertext, 29, scorer);
+            assert_eq!(key, b"Terminator X: Bring the noise");
+        }
+    }
+
+    #[test]
+    fn every_estimator_finds_29() {
+        use crate::utils::parse_file_base64;
+
+        let ciphertext = parse_file_base64("src/set_one/1-6.txt");
+        let options = KeyLengthOptions::default();
+
+        for estimator in [
+            KeyLengthEstimator::HammingDistance,
+            KeyLengthEstimator::Coincidence,
+            KeyLengthEstimator::Kasiski,
+            KeyLengthEstimator::Ensemble,
+        ] {
+            let candidates = estimator.estimate(&ciphertext, &options).unwrap();
+            println!("{:?}: {:?}", estimator, candidates);
+            assert_eq!(candidates[0].0, 29, "{:?}", estimator);
+
+            let (_, key) = brute_force_ciphertext(&ciphertext, candidates[0].0);
+            assert_eq!(key, b"Terminator X: Bring the noise");
+        }
+    }
+
+    #[test]
+    fn multiples_rank_below_the_key_length() {
+        use crate::utils::{lyrics, parse_file_base64};
+
+        let ciphertext = parse_file_base64("src/set_one/1-6.txt");
+        let options = KeyLengthOptions {
+            max_length: 90,
+            ..KeyLengthOptions::default()
+        };
+
+        for estimator in [KeyLengthEstimator::Coincidence, KeyLengthEstimator::Kasiski] {
+            let candidates = estimator.estimate(&ciphertext, &options).unwrap();
+            println!("{:?}: {:?}", estimator, candidates);
+            assert_eq!(candidates[0].0, 29, "{:?}", estimator);
+
+            // doubling the length halves the evidence instead of matching it
+            let score = |key_length| candidates.iter().find(|(l, _)| *l == key_length).unwrap().1;
+            assert!(score(58) < 0.6 * score(29), "{:?}", estimator);
+        }
+
+        // every length looks like english under a single-byte key, the shortest should win
+        let single_byte = xor_with_key(&lyrics(), b"X");
+        let candidates = guess_key_length_by_coincidence(&single_byte, &options).unwrap();
+        assert_eq!(candidates[0].0, 1);
+    }
+
+    #[test]
+    fn short_ciphertexts() {
+        use crate::utils::parse_file_base64;
+
+        // ten repetitions of the key
+        let ciphertext = &parse_file_base64("src/set_one/1-6.txt")[..290];
+        let options = KeyLengthOptions::default();
+
+        for estimator in [
+            KeyLengthEstimator::HammingDistance,
+            KeyLengthEstimator::Coincidence,
+            KeyLengthEstimator::Kasiski,
+            KeyLengthEstimator::Ensemble,
+        ] {
+            let candidates = estimator.estimate(ciphertext, &options).unwrap();
+            println!("{:?}: {:?}", estimator, candidates);
+            assert_eq!(candidates[0].0, 29, "{:?}", estimator);
+        }
+    }
+
+    #[test]
+    fn every_candidate_can_be_brute_forced() {
+        // "the " repeats 12 bytes apart, so Kasiski has gaps to work with
+        let ciphertext = &xor_with_key(b"the cat and the hat and the bat!", b"ICE");
+        let options = KeyLengthOptions {
+            count: 40,
+            ..KeyLengthOptions::default()
+        };
+
+        for estimator in [
+            KeyLengthEstimator::HammingDistance,
+            KeyLengthEstimator::Coincidence,
+            KeyLengthEstimator::Kasiski,
+            KeyLengthEstimator::Ensemble,
+        ] {
+            let candidates = estimator.estimate(ciphertext, &options).unwrap();
+            for (key_length, _) in candidates {
+                assert!(key_length < ciphertext.len(), "{:?}", estimator);
+                let (plaintext, key) = brute_force_ciphertext(ciphertext, key_length);
+                assert_eq!(plaintext.len(), ciphertext.len());
+                assert_eq!(key.len(), key_length);
+            }
+        }
+
+        assert_eq!(
+            brute_force_ciphertext(ciphertext, 0),
+            (ciphertext.to_vec(), vec![])
+        );
+        assert_eq!(brute_force_ciphertext(ciphertext, 33).1, vec![]);
+        assert!(transpose::<u8>(vec![]).is_empty());
+    }
+
+    #[test]
+    fn beam_search_fixes_weak_columns() {
+        use crate::scoring::NgramScorer;
+        use crate::utils::lyrics;
+
+        // bigrams from the 1.7 lyrics, tested on unrelated text
+        let bigrams = NgramScorer::bigrams(&lyrics());
+
+        let plaintext = b"The very first well-documented description of a polyalphabetic cipher was by Leon Battista Alberti around 1467 and used a metal cipher disk to switch between cipher alphabets. Alberti's system only switched alphabets after several words, and switches were indicated by writing the letter of the corresponding alphabet in the ciphertext.";
+        let key = b"ICE ICE BABY!";
+        let ciphertext = xor_with_key(plaintext, key);
+
+        let (_, greedy_key) = brute_force_ciphertext(&ciphertext, key.len());
+        let results = beam_search_key(
+            &ciphertext,
+            key.len(),
+            &LetterFrequency::default(),
+            &bigrams,
+            &BeamSearchOptions::default(),
+        );
+
+        println!("greedy: {:?}", String::from_utf8_lossy(&greedy_key));
+        for (key, _, score) in results.iter() {
+            println!("{:?} {}", String::from_utf8_lossy(key), score);
+        }
+        // frequency analysis alone gets a column wrong on this little text
+        assert_ne!(greedy_key, key);
+        assert_eq!(results.len(), 5);
+        assert!(results.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+        assert_eq!(results[0].0, key);
+        assert_eq!(results[0].1, plaintext);
+    }
 }
@@ -0,0 +1,269 @@
+// Ways of scoring how much a candidate plaintext looks like a language (English unless given
+// a trained model), so the xor breakers can pick the metric that suits their data
+
+pub use scoring::*;
+
+#[allow(clippy::module_inception)]
+pub mod scoring {
+    use crate::language_model::LanguageModel;
+
+    /// Anything that can rank candidate plaintexts.
+    /// Lower scores mean more likely to be the real plaintext.
+    pub trait PlaintextScorer {
+        fn score(&self, plaintext: &[u8]) -> f32;
+    }
+
+    fn byte_counts(plaintext: &[u8]) -> [usize; 256] {
+        let mut counts = [0; 256];
+        for byte in plaintext {
+            counts[*byte as usize] += 1;
+        }
+        counts
+    }
+
+    /// Sum of the absolute differences between the byte frequencies of the plaintext and
+    /// the model's, over the bytes that appear in the plaintext
+    #[derive(Debug, Clone)]
+    pub struct LetterFrequency {
+        frequencies: [f32; 256],
+    }
+
+    impl LetterFrequency {
+        pub fn from_model(model: &LanguageModel) -> Self {
+            LetterFrequency {
+                frequencies: *model.unigrams(),
+            }
+        }
+    }
+
+    impl Default for LetterFrequency {
+        fn default() -> Self {
+            Self::from_model(LanguageModel::shared_english())
+        }
+    }
+
+    impl PlaintextScorer for LetterFrequency {
+        fn score(&self, plaintext: &[u8]) -> f32 {
+            byte_counts(plaintext)
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(byte, count)| {
+                    (self.frequencies[byte] - *count as f32 / plaintext.len() as f32).abs()
+                })
+                .sum()
+        }
+    }
+
+    /// Pearson's chi-squared statistic of the byte counts against the model.
+    /// Bytes the model never saw are expected a tiny amount so they get punished, not divided by zero.
+    #[derive(Debug, Clone)]
+    pub struct ChiSquared {
+        frequencies: [f32; 256],
+    }
+
+    /// Stand-in frequency for bytes the model never saw
+    const UNSEEN_FREQUENCY: f32 = 1e-6;
+
+    impl ChiSquared {
+        pub fn from_model(model: &LanguageModel) -> Self {
+            ChiSquared {
+                frequencies: *model.unigrams(),
+            }
+        }
+    }
+
+    impl Default for ChiSquared {
+        fn default() -> Self {
+            Self::from_model(LanguageModel::shared_english())
+        }
+    }
+
+    impl PlaintextScorer for ChiSquared {
+        fn score(&self, plaintext: &[u8]) -> f32 {
+            byte_counts(plaintext)
+                .iter()
+                .zip(self.frequencies.iter())
+                .map(|(count, frequency)| {
+                    let expected = frequency.max(UNSEEN_FREQUENCY) * plaintext.len() as f32;
+                    (*count as f32 - expected).powi(2) / expected
+                })
+                .sum()
+        }
+    }
+
+    /// Average negative log-likelihood of each byte under the model's byte frequencies.
+    /// `smoothing` is added to every frequency so unseen bytes cost a lot instead of infinity.
+    #[derive(Debug, Clone)]
+    pub struct LogLikelihood {
+        frequencies: [f32; 256],
+        pub smoothing: f32,
+    }
+
+    impl LogLikelihood {
+        pub fn from_model(model: &LanguageModel) -> Self {
+            LogLikelihood {
+                frequencies: *model.unigrams(),
+                smoothing: 1e-4,
+            }
+        }
+    }
+
+    impl Default for LogLikelihood {
+        fn default() -> Self {
+            Self::from_model(LanguageModel::shared_english())
+        }
+    }
+
+    impl PlaintextScorer for LogLikelihood {
+        fn score(&self, plaintext: &[u8]) -> f32 {
+            if plaintext.is_empty() {
+                return 0.0;
+            }
+
+            let total = 1.0 + 256.0 * self.smoothing;
+            let log_likelihood = plaintext
+                .iter()
+                .map(|byte| ((self.frequencies[*byte as usize] + self.smoothing) / total).ln())
+                .sum::<f32>();
+
+            -log_likelihood / plaintext.len() as f32
+        }
+    }
+
+    /// Share of bytes that aren't printable ASCII or common whitespace.
+    /// Cheap and language agnostic, but can't tell apart keys that only flip letter case.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PrintableRatio;
+
+    impl PlaintextScorer for PrintableRatio {
+        fn score(&self, plaintext: &[u8]) -> f32 {
+            if plaintext.is_empty() {
+                return 0.0;
+            }
+
+            let printable = plaintext
+                .iter()
+                .filter(|byte| {
+                    byte.is_ascii_graphic() || matches!(byte, b' ' | b'\n' | b'\r' | b'\t')
+                })
+                .count();
+
+            1.0 - printable as f32 / plaintext.len() as f32
+        }
+    }
+
+    /// Average negative log-probability of every overlapping n-gram under a trained model.
+    /// Letters are case folded in both the model and the plaintext. Only makes sense on
+    /// contiguous text, not on the strided columns of a repeating-key xor.
+    #[derive(Debug, Clone)]
+    pub struct NgramScorer {
+        model: LanguageModel,
+    }
+
+    impl NgramScorer {
+        /// Panics if the model has no n-grams
+        pub fn from_model(model: LanguageModel) -> Self {
+            assert!(model.order() > 0, "n-grams need at least one byte");
+            NgramScorer {
+                model: model.case_folded(),
+            }
+        }
+
+        pub fn from_corpus(n: usize, corpus: &[u8]) -> Self {
+            Self::from_model(LanguageModel::train(corpus, n))
+        }
+
+        pub fn bigrams(corpus: &[u8]) -> Self {
+            Self::from_corpus(2, corpus)
+        }
+
+        pub fn quadgrams(corpus: &[u8]) -> Self {
+            Self::from_corpus(4, corpus)
+        }
+    }
+
+    impl PlaintextScorer for NgramScorer {
+        fn score(&self, plaintext: &[u8]) -> f32 {
+            let plaintext = plaintext.to_ascii_lowercase();
+            let n = self.model.order();
+            if plaintext.len() < n {
+                return -self.model.unseen_log_probability();
+            }
+
+            let windows = plaintext.windows(n);
+            let count = windows.len();
+            let log_probability = windows
+                .map(|ngram| self.model.ngram_log_probability(ngram))
+                .sum::<f32>();
+
+            -log_probability / count as f32
+        }
+    }
+
+    #[test]
+    fn test_delta_from_english() {
+        let scorer = LetterFrequency::default();
+
+        let potential_plaintext = String::from("The very first well-documented description of a polyalphabetic cipher was by Leon Battista Alberti around 1467 and used a metal");
+        println!(
+            "plaintext: {}",
+            scorer.score(potential_plaintext.as_bytes())
+        );
+
+        let not_likely_plaintext = String::from("Dlc fipi jgbwr gijv-hmmykorroh bowabmndmmx sd k tmvcyvtfkfcdma mmnrip geq lc Josl Lerdmqde Yvfcbxg kvmerb 1467 krb ewcn e koxyv");
+        println!(
+            "not plaintext: {}",
+            scorer.score(not_likely_plaintext.as_bytes())
+        );
+
+        let not_plaintext = String::from("Cmm znwg jrwax fjtp-mtkyvjvxni libhzmyyqsw tn e yttcjqxljgmxrh kmymmv ffa fh Qmsw Gixcnaxj Ftfnwbm jwwywi 1467 irm zaim f uicft");
+        println!("not plaintext: {}", scorer.score(not_plaintext.as_bytes()));
+    }
+
+    #[test]
+    fn every_scorer_finds_single_byte_key() {
+        use crate::repeating_key_xor::guess_xord_key_with;
+        use crate::utils::lyrics;
+
+        let plaintext =
+            b"Now that the party is jumping, with the bass kicked in and the Vega's are pumpin'";
+        let ciphertext = plaintext
+            .iter()
+            .map(|byte| byte ^ 0x5a)
+            .collect::<Vec<u8>>();
+
+        let lyrics = lyrics();
+        let scorers: Vec<Box<dyn PlaintextScorer>> = vec![
+            Box::new(LetterFrequency::default()),
+            Box::new(ChiSquared::default()),
+            Box::new(LogLikelihood::default()),
+            Box::new(ChiSquared::from_model(&LanguageModel::train(&lyrics, 0))),
+            Box::new(NgramScorer::bigrams(&lyrics)),
+            Box::new(NgramScorer::quadgrams(&lyrics)),
+        ];
+
+        for scorer in scorers.iter() {
+            assert_eq!(guess_xord_key_with(&ciphertext, scorer.as_ref()), 0x5a);
+        }
+    }
+
+    #[test]
+    fn ngrams_ignore_case() {
+        let scorer = NgramScorer::bigrams(b"The quick brown fox. THE LAZY DOG.");
+
+        assert_eq!(scorer.score(b"the fox"), scorer.score(b"THE FOX"));
+        // "he" and "HE" were counted as one bigram
+        assert!(scorer.score(b"he") < scorer.score(b"ox"));
+        assert!(std::ptr::eq(
+            LanguageModel::shared_english(),
+            LanguageModel::shared_english()
+        ));
+    }
+
+    #[test]
+    fn printable_ratio() {
+        assert_eq!(PrintableRatio.score(b"plain text\n"), 0.0);
+        assert_eq!(PrintableRatio.score(b"ab\x00\xff"), 0.5);
+    }
+}
@@ -1,52 +1,104 @@
-#[allow(dead_code)]
+#[allow(unused_imports)]
 pub use aes_ecb::*;
 
 #[allow(dead_code)]
+#[allow(clippy::module_inception)]
 pub mod aes_ecb {
-    use aes::{
-        cipher::{generic_array::GenericArray, BlockDecrypt, KeyInit},
-        Aes128, Block,
-    };
+    use crate::block_cipher::{Aes128, AesCore, BlockCipher, Ecb, BLOCK_SIZE_BYTES};
+    use crate::error::CryptopalsError;
+    use ::aes::cipher::inout::InOutBuf;
+    use ::aes::cipher::typenum::U16;
 
+    /// Thin adapter over `BlockCipher<Ecb>` working on raw bytes
     pub struct AesEcb128 {
-        key: Block,
-        cipher: Aes128,
+        cipher: BlockCipher<Ecb, Aes128>,
     }
 
     impl AesEcb128 {
         pub fn new(key: &[u8]) -> Self {
-            assert!(key.len() <= 16);
-
-            let mut key_array = GenericArray::from([0u8; 16]);
-            key_array.copy_from_slice(key);
+            Self::try_new(key).expect("AesEcb128 needs a 16 byte key")
+        }
 
-            let cipher = Aes128::new(&key_array);
+        pub fn try_new(key: &[u8]) -> Result<Self, CryptopalsError> {
+            let cipher = BlockCipher::from_cipher(Ecb, Aes128::from_key(key)?);
 
-            AesEcb128 {
-                key: key_array,
-                cipher,
-            }
+            Ok(AesEcb128 { cipher })
         }
 
-        /// This should write plaintext to a provided input buffer, not allocate mem itself
+        /// Convenience wrapper around `decrypt_in_place` that allocates the output
         pub fn decrypt(&self, ciphertext: &[u8]) -> Vec<u8> {
-            let encrypted_blocks = ciphertext.chunks(16);
+            self.try_decrypt(ciphertext).unwrap()
+        }
 
-            // allocate mutable buffer
-            let mut plaintext = Vec::<Block>::with_capacity(encrypted_blocks.len());
+        /// Errors instead of panicking when the ciphertext isn't a whole number of blocks
+        pub fn try_decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptopalsError> {
+            let mut plaintext = ciphertext.to_vec();
+            self.decrypt_in_place(&mut plaintext)?;
+            Ok(plaintext)
+        }
+
+        /// Writes the plaintext into the front of `out` without allocating,
+        /// returning how many bytes were written
+        pub fn decrypt_into(
+            &self,
+            ciphertext: &[u8],
+            out: &mut [u8],
+        ) -> Result<usize, CryptopalsError> {
+            check_alignment(ciphertext)?;
+            if out.len() < ciphertext.len() {
+                return Err(CryptopalsError::BufferTooSmall {
+                    needed: ciphertext.len() / BLOCK_SIZE_BYTES,
+                    got: out.len() / BLOCK_SIZE_BYTES,
+                });
+            }
 
-            // fill buffer
-            plaintext.extend(
-                encrypted_blocks
-                    .map(Block::clone_from_slice)
-                    .collect::<Vec<Block>>(),
-            );
+            let out = &mut out[..ciphertext.len()];
+            out.copy_from_slice(ciphertext);
+            self.decrypt_in_place(out)?;
+            Ok(ciphertext.len())
+        }
 
-            // decrypt (parallelizable)
-            self.cipher.decrypt_blocks(&mut plaintext);
+        /// Decrypts the buffer in place, handing every block to the cipher in one batch
+        pub fn decrypt_in_place(&self, buffer: &mut [u8]) -> Result<(), CryptopalsError> {
+            check_alignment(buffer)?;
 
-            // flatten out of blocks, same length as the slice
-            plaintext.into_iter().flatten().collect::<Vec<u8>>()
+            // views the bytes as blocks without copying them
+            let (blocks, _) = InOutBuf::from(buffer).into_chunks::<U16>();
+            self.cipher.cipher().decrypt_blocks(blocks.into_out());
+            Ok(())
         }
     }
+
+    fn check_alignment(bytes: &[u8]) -> Result<(), CryptopalsError> {
+        if !bytes.len().is_multiple_of(BLOCK_SIZE_BYTES) {
+            return Err(CryptopalsError::BlockAlignment {
+                len: bytes.len(),
+                block_size: BLOCK_SIZE_BYTES,
+            });
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn decrypts_fips_197_vector() {
+        let key = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let plaintext = hex::decode("00112233445566778899aabbccddeeff").unwrap();
+        let ciphertext = hex::decode("69c4e0d86a7b0430d8cdb78070b4c55a").unwrap();
+        let cipher = AesEcb128::new(&key);
+
+        // several blocks so the batch covers more than one
+        let mut buffer = ciphertext.repeat(5);
+        cipher.decrypt_in_place(&mut buffer).unwrap();
+        assert_eq!(buffer, plaintext.repeat(5));
+
+        let mut out = [0u8; 48];
+        assert_eq!(cipher.decrypt_into(&ciphertext, &mut out).unwrap(), 16);
+        assert_eq!(out[..16], plaintext[..]);
+        assert!(out[16..].iter().all(|byte| *byte == 0));
+
+        assert!(matches!(
+            cipher.decrypt_in_place(&mut buffer[..20]),
+            Err(CryptopalsError::BlockAlignment { len: 20, .. })
+        ));
+    }
 }
@@ -11,7 +11,7 @@ mod repeating_key_xor_tests {
         let ciphertext = hex::decode("1729316330212a223f6323262c363a63273b3b613e362c242625742c37313161202b24742f202d63253b246f740a6123222f2063353b6323316320362f2474372e742f282226613d2d613563253b332474332d352024742a2f740d181763363d372974092e3c2d6f740a66396331262635203a613236223f2a2f336332202c2a31276f7417293d30613d3061332c283a2461202c613626613563262626202063353d2e247a").unwrap();
         let key_lengths = guess_key_length(&ciphertext, 5);
 
-        for key_length in key_lengths {
+        for (key_length, _) in key_lengths {
             let (plaintext, key) = brute_force_ciphertext(ciphertext.as_slice(), key_length);
 
             println!(
@@ -38,7 +38,8 @@ mod repeating_key_xor_tests {
         let ciphertext = base64::decode(file.replace('\n', "")).unwrap();
         let key_lengths = guess_key_length(&ciphertext, 40);
 
-        let (plaintext, key) = brute_force_ciphertext(ciphertext.as_slice(), key_lengths[0]);
+        assert_eq!(key_lengths[0].0, 29);
+        let (plaintext, key) = brute_force_ciphertext(ciphertext.as_slice(), key_lengths[0].0);
 
         println!(
             "KEY:\n\"{}\"\n{:?}\n",
@@ -110,6 +111,56 @@ mod aes_ecb_tests {
         println!("PLAINTEXT:\n\n{}", String::from_utf8(plaintext).unwrap());
     }
 
+    #[test]
+    fn decrypt_without_allocating() {
+        use crate::set_one::aes_ecb::*;
+        use crate::utils::parse_file_base64;
+
+        let ciphertext = parse_file_base64("src/set_one/1-7.txt");
+        let cipher = AesEcb128::new(b"YELLOW SUBMARINE");
+        let expected = cipher.decrypt(&ciphertext);
+
+        let mut out = vec![0u8; ciphertext.len() + 16];
+        assert_eq!(
+            cipher.decrypt_into(&ciphertext, &mut out).unwrap(),
+            ciphertext.len()
+        );
+        assert_eq!(out[..ciphertext.len()], expected[..]);
+        assert!(cipher.decrypt_into(&ciphertext, &mut out[..32]).is_err());
+
+        let mut buffer = ciphertext.clone();
+        cipher.decrypt_in_place(&mut buffer).unwrap();
+        assert_eq!(buffer, expected);
+        assert!(buffer.starts_with(b"I'm back and I'm ringin' the bell"));
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        use crate::error::CryptopalsError;
+        use crate::set_one::aes_ecb::*;
+        use crate::utils::try_parse_file_base64;
+
+        assert!(matches!(
+            AesEcb128::try_new(b"YELLOW"),
+            Err(CryptopalsError::KeyLength(6))
+        ));
+
+        let cipher = AesEcb128::new(b"YELLOW SUBMARINE");
+        assert!(matches!(
+            cipher.try_decrypt(&[0u8; 20]),
+            Err(CryptopalsError::BlockAlignment { len: 20, .. })
+        ));
+
+        assert!(matches!(
+            try_parse_file_base64("src/set_one/missing.txt"),
+            Err(CryptopalsError::Io(_))
+        ));
+        assert!(matches!(
+            try_parse_file_base64("src/set_one/1-4.txt"),
+            Err(CryptopalsError::Base64(_))
+        ));
+    }
+
     // 1-8
     #[test]
     fn find_ecb_ciphertext_from_many() {
@@ -148,7 +199,7 @@ mod aes_ecb_tests {
                         hamming_distance(combination[0], combination[1]) as f32 / blocksize as f32;
                 }
 
-                hamming_distances.push((index, distance as f32 / blocks_len as f32));
+                hamming_distances.push((index, distance / blocks_len as f32));
             }
 
             hamming_distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
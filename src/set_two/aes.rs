@@ -1,117 +1,116 @@
 pub use self::aes::*;
 
 #[allow(dead_code)]
+#[allow(clippy::module_inception)]
 pub mod aes {
-    pub use ::aes::Block;
-    use ::aes::{
-        cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit},
-        Aes128,
+    pub use crate::block_cipher::Block;
+    use crate::block_cipher::{
+        AesCore, BlockCipher, BlockMode, Cbc, CryptopalsError, Ctr, Ecb, PaddingError,
     };
 
-    pub const BLOCK_SIZE_BYTES: usize = 16;
-
+    /// Thin adapter over `BlockCipher` that picks its mode at runtime.
+    /// Despite the name it takes 128, 192 or 256 bit keys.
     pub struct AES128 {
-        key: Block,
-        cipher: Aes128,
-        cipher_mode: CipherMode,
+        cipher: BlockCipher<CipherMode>,
     }
 
+    #[allow(clippy::upper_case_acronyms)]
     pub enum CipherMode {
         /// Electronic Code Book
         ECB,
         /// CBC needs an initialization vector
         /// @John is it safe/required to store IV in the struct?
         CBC(Block),
+        /// Counter mode, works on arbitrary-length bytes through `encrypt_stream`/`decrypt_stream`
+        CTR(Ctr),
     }
 
-    impl AES128 {
-        pub fn new(cipher_mode: CipherMode, key: &[u8]) -> Self {
-            assert!(key.len() <= BLOCK_SIZE_BYTES);
-
-            let mut key_array = GenericArray::from([0u8; BLOCK_SIZE_BYTES]);
-            key_array.copy_from_slice(key);
+    impl BlockMode for CipherMode {
+        fn encrypt<C: AesCore>(&self, cipher: &C, plaintext: &[Block], out: &mut [Block]) {
+            match self {
+                CipherMode::ECB => Ecb.encrypt(cipher, plaintext, out),
+                CipherMode::CBC(iv) => Cbc::new(*iv).encrypt(cipher, plaintext, out),
+                CipherMode::CTR(ctr) => ctr.encrypt(cipher, plaintext, out),
+            }
+        }
 
-            let cipher = Aes128::new(&key_array);
+        fn decrypt<C: AesCore>(&self, cipher: &C, ciphertext: &[Block], out: &mut [Block]) {
+            match self {
+                CipherMode::ECB => Ecb.decrypt(cipher, ciphertext, out),
+                CipherMode::CBC(iv) => Cbc::new(*iv).decrypt(cipher, ciphertext, out),
+                CipherMode::CTR(ctr) => ctr.decrypt(cipher, ciphertext, out),
+            }
+        }
 
-            AES128 {
-                key: key_array,
-                cipher,
-                cipher_mode,
+        fn keystream_block<C: AesCore>(&self, cipher: &C, index: u64) -> Option<Block> {
+            match self {
+                CipherMode::CTR(ctr) => ctr.keystream_block(cipher, index),
+                _ => None,
             }
         }
 
-        /// This should write plaintext to a provided input buffer, not allocate mem itself
-        pub fn decrypt(&self, ciphertext: &[Block], out: &mut [Block]) {
-            assert_eq!(ciphertext.len(), out.len());
+        fn is_stream_mode(&self) -> bool {
+            matches!(self, CipherMode::CTR(_))
+        }
 
-            match self.cipher_mode {
+        fn advance(&mut self, ciphertext: &[Block]) {
+            match self {
+                CipherMode::ECB => {}
                 CipherMode::CBC(iv) => {
-                    let mut prev_block = iv;
-
-                    // zip ciphertext with output
-                    for (ciphertext_block, out_block) in ciphertext.iter().zip(out.iter_mut()) {
-                        // copy ciphertext block to output
-                        out_block.copy_from_slice(ciphertext_block);
-
-                        // decrypt output
-                        self.cipher.decrypt_block(out_block);
-
-                        // xor output with prev block/iv
-                        for (out, prev) in out_block.iter_mut().zip(prev_block.iter()) {
-                            *out ^= *prev;
-                        }
-                        // update prev block
-                        prev_block = *ciphertext_block;
+                    if let Some(last) = ciphertext.last() {
+                        *iv = *last;
                     }
                 }
-                CipherMode::ECB => {
-                    // copy ciphertext into output
-                    for (ciphertext_block, out) in ciphertext.iter().zip(out.iter_mut()) {
-                        out.copy_from_slice(ciphertext_block);
-                    }
-                    // parallel decrypt
-                    self.cipher.decrypt_blocks(out);
-                }
+                CipherMode::CTR(ctr) => ctr.advance(ciphertext),
             }
         }
+    }
 
-        /// Encrypts a slice of Blocks into a provided output buffer
-        /// UNTESTSED
-        pub fn encrypt(&self, plaintext: &[Block], out: &mut [Block]) {
-            assert!(out.len() >= plaintext.len());
+    impl AES128 {
+        pub fn new(cipher_mode: CipherMode, key: &[u8]) -> Self {
+            Self::try_new(cipher_mode, key).expect("AES needs a 16, 24 or 32 byte key")
+        }
 
-            match self.cipher_mode {
-                CipherMode::CBC(iv) => {
-                    let mut prev_block = iv;
+        /// Picks AES-128/192/256 from the key length, erroring on anything else
+        pub fn try_new(cipher_mode: CipherMode, key: &[u8]) -> Result<Self, CryptopalsError> {
+            let cipher = BlockCipher::new(cipher_mode, key)?;
 
-                    for (plaintext_block, out_block) in plaintext.iter().zip(out) {
-                        // copy plaintext block to buffer
-                        out_block.copy_from_slice(plaintext_block);
+            Ok(AES128 { cipher })
+        }
 
-                        // xor buffer with prev block/iv
-                        for (out, prev) in out_block.iter_mut().zip(prev_block.iter()) {
-                            *out ^= *prev;
-                        }
+        /// This should write plaintext to a provided input buffer, not allocate mem itself
+        pub fn decrypt(&self, ciphertext: &[Block], out: &mut [Block]) {
+            assert_eq!(ciphertext.len(), out.len());
+            self.cipher.decrypt(ciphertext, out).unwrap();
+        }
 
-                        // encrypt the block
-                        self.cipher.encrypt_block(out_block);
+        /// Encrypts a slice of Blocks into a provided output buffer
+        pub fn encrypt(&self, plaintext: &[Block], out: &mut [Block]) {
+            self.cipher.encrypt(plaintext, out).unwrap();
+        }
 
-                        // update prev block
-                        prev_block = *out_block;
-                    }
-                }
-                CipherMode::ECB => {
-                    // copy plaintext into output
-                    for (plaintext_block, out_block) in
-                        plaintext.iter().zip(out.iter_mut())
-                    {
-                        out_block.copy_from_slice(plaintext_block);
-                    }
+        /// Encrypts bytes with PKCS#7 padding (no padding in CTR mode)
+        pub fn encrypt_bytes(&self, plaintext: &[u8]) -> Vec<u8> {
+            self.cipher.encrypt_bytes(plaintext)
+        }
 
-                    // parallel encrypt
-                    self.cipher.encrypt_blocks(out);
-                }
-            }
+        /// Decrypts bytes and validates/strips PKCS#7 padding (no padding in CTR mode)
+        pub fn decrypt_bytes(&self, ciphertext: &[u8]) -> Result<Vec<u8>, PaddingError> {
+            self.cipher.decrypt_bytes(ciphertext)
+        }
+
+        /// Encrypts arbitrary-length bytes, only valid in CTR mode
+        pub fn encrypt_stream(&self, plaintext: &[u8]) -> Vec<u8> {
+            self.cipher
+                .encrypt_stream(plaintext)
+                .expect("encrypt_stream needs CTR mode")
+        }
+
+        /// Decrypts arbitrary-length bytes, only valid in CTR mode
+        pub fn decrypt_stream(&self, ciphertext: &[u8]) -> Vec<u8> {
+            self.cipher
+                .decrypt_stream(ciphertext)
+                .expect("decrypt_stream needs CTR mode")
         }
     }
 }
@@ -1,13 +1,8 @@
-mod aes;
+pub(crate) mod aes;
 
-/// 2.1
-#[allow(dead_code)]
-pub fn pkcs_padding(input: &[u8], block_size: usize) -> Vec<u8> {
-    let mut output = input.to_vec();
-    let padding = block_size - (input.len() % block_size);
-    output.extend(vec![padding as u8; padding]);
-    output
-}
+// 2.1 lives in block_cipher now, re-exported so the challenge paths still resolve
+#[allow(unused_imports)]
+pub use crate::block_cipher::{pkcs_padding, pkcs_unpadding, PaddingError};
 
 /// 2.1
 #[test]
@@ -17,6 +12,36 @@ fn test_pkcs_padding() {
     assert_eq!(pkcs_padding(input, 20), expected);
 }
 
+/// 2.1
+#[test]
+fn test_pkcs_unpadding() {
+    assert_eq!(
+        pkcs_unpadding(b"ICE ICE BABY\x04\x04\x04\x04", 16),
+        Ok(&b"ICE ICE BABY"[..])
+    );
+    assert_eq!(pkcs_unpadding(&[16u8; 16], 16), Ok(&[][..]));
+    assert_eq!(
+        pkcs_unpadding(b"ICE ICE BABY\x05\x05\x05\x05", 16),
+        Err(PaddingError::InconsistentPadding)
+    );
+    assert_eq!(
+        pkcs_unpadding(b"ICE ICE BABY\x01\x02\x03\x04", 16),
+        Err(PaddingError::InconsistentPadding)
+    );
+    assert_eq!(
+        pkcs_unpadding(b"ICE ICE BABY\x00\x00\x00\x00", 16),
+        Err(PaddingError::InvalidPadByte(0))
+    );
+    assert_eq!(
+        pkcs_unpadding(&[17u8; 16], 16),
+        Err(PaddingError::InvalidPadByte(17))
+    );
+    assert_eq!(
+        pkcs_unpadding(b"ICE ICE BABY", 16),
+        Err(PaddingError::InvalidLength(12))
+    );
+}
+
 /// 2.2
 #[test]
 fn aes_cbc_decrypt() {
@@ -55,41 +80,132 @@ fn aes_cbc_decrypt() {
     });
 }
 
+/// 2.2
+#[test]
+fn aes_cbc_decrypt_bytes() {
+    use crate::set_two::aes::{Block, CipherMode, AES128};
+    use crate::utils::parse_file_base64;
+
+    let ciphertext = parse_file_base64("src/set_two/2-2.txt");
+    let cipher = AES128::new(CipherMode::CBC(Block::default()), b"YELLOW SUBMARINE");
+
+    let plaintext = cipher.decrypt_bytes(&ciphertext).unwrap();
+    assert!(plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+    assert!(plaintext.ends_with(b"Play that funky music \n"));
+
+    assert_eq!(cipher.encrypt_bytes(&plaintext), ciphertext);
+}
+
+#[test]
+fn cipher_modes_across_key_sizes() {
+    use crate::block_cipher::Ctr;
+    use crate::set_two::aes::{Block, CipherMode, AES128};
+    use crate::utils::*;
+
+    let plaintext = b"It's been a hard day's night, and I been working like a dog".to_vec();
+    let iv = Block::from([3u8; 16]);
+
+    for key_len in [16, 24, 32] {
+        let key = vec![9u8; key_len];
+        for mode in [
+            CipherMode::ECB,
+            CipherMode::CBC(iv),
+            CipherMode::CTR(Ctr::cryptopals(5)),
+        ] {
+            let cipher = AES128::new(mode, &key);
+            let (input, mut ciphertext) = text_to_io(plaintext.clone());
+            let mut decrypted = output_from_block_count(input.len());
+
+            cipher.encrypt(&input, &mut ciphertext);
+            cipher.decrypt(&ciphertext, &mut decrypted);
+            assert_eq!(input, decrypted);
+        }
+    }
+
+    assert!(AES128::try_new(CipherMode::ECB, b"YELLOW").is_err());
+}
+
 // 2.3
 #[allow(dead_code)]
-mod detection_oracle {
+pub(crate) mod detection_oracle {
+    use crate::chosen_plaintext::EncryptionMode;
+    use crate::oracle::EncryptionOracle;
     use crate::set_two::aes::*;
     use crate::utils::*;
+    use rand::rngs::{OsRng, StdRng};
+    use rand::{Rng, RngCore, SeedableRng};
+
+    /// `random_encryptor` as an `EncryptionOracle`, remembering the mode it picked last
+    /// so detection can be checked against the truth
+    pub struct RandomEncryptor {
+        rng: StdRng,
+        last_mode: Option<EncryptionMode>,
+    }
+
+    impl RandomEncryptor {
+        pub fn new() -> Self {
+            Self::from_rng(&mut OsRng)
+        }
+
+        /// Seeds the generator every key, mode, IV and filler byte is drawn from with `rng`
+        pub fn from_rng(rng: &mut impl RngCore) -> Self {
+            RandomEncryptor {
+                rng: StdRng::from_rng(rng).expect("rng failed to produce a seed"),
+                last_mode: None,
+            }
+        }
+
+        pub fn last_mode(&self) -> Option<EncryptionMode> {
+            self.last_mode
+        }
+    }
+
+    impl Default for RandomEncryptor {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl EncryptionOracle for RandomEncryptor {
+        fn encrypt(&mut self, plaintext: &[u8]) -> Vec<u8> {
+            let (mode, ciphertext) = random_encryptor_from_rng(plaintext, &mut self.rng);
+            self.last_mode = Some(mode);
+            ciphertext.into_iter().flatten().collect()
+        }
+    }
+
+    /// Encrypts under a random key with ECB or CBC picked at random, returning which one it used
+    pub fn random_encryptor(plaintext: &[u8]) -> (EncryptionMode, Vec<Block>) {
+        random_encryptor_from_rng(plaintext, &mut OsRng)
+    }
 
-    // pub struct RandomEncryptor {}
-    pub fn random_encryptor(plaintext: &[u8]) -> Vec<Block> {
+    /// `random_encryptor` with every random choice drawn from `rng`
+    pub fn random_encryptor_from_rng(
+        plaintext: &[u8],
+        rng: &mut impl RngCore,
+    ) -> (EncryptionMode, Vec<Block>) {
         // generate randon key and blockmode
-        let random_key = Block::from(rand::random::<[u8; 16]>());
+        let random_key = Block::from(rng.gen::<[u8; 16]>());
 
-        let encryption_mode_is_ecb = rand::random::<bool>();
-        println!(
-            "SECRET!: encryption mode is {}",
-            if encryption_mode_is_ecb { "ECB" } else { "CBC" }
-        );
-        println!();
+        let encryption_mode_is_ecb = rng.gen::<bool>();
 
         // generate random length suffix
-        let suffix_len = (rand::random::<u8>() % 5) + 5;
-        let mut suffix = Vec::<u8>::with_capacity(suffix_len as usize);
+        let suffix_len = rng.gen_range(5..10);
+        let mut suffix = Vec::<u8>::with_capacity(suffix_len);
         for _ in 0..suffix_len {
-            suffix.push(rand::random::<u8>());
+            suffix.push(rng.gen::<u8>());
         }
 
         // generate random length prefix
-        let prefix_len = (rand::random::<u8>() % 5) + 5;
-        let mut prefix = Vec::<u8>::with_capacity(prefix_len as usize);
+        let prefix_len = rng.gen_range(5..10);
+        let mut prefix = Vec::<u8>::with_capacity(prefix_len);
         for _ in 0..prefix_len {
-            prefix.push(rand::random::<u8>());
+            prefix.push(rng.gen::<u8>());
         }
 
         // add prefix and suffix to plaintext
         let mut plaintext_with_rand_bytes =
-            Vec::<u8>::with_capacity(plaintext.len() + prefix_len as usize + suffix_len as usize);
+            Vec::<u8>::with_capacity(plaintext.len() + prefix_len + suffix_len);
         plaintext_with_rand_bytes.extend(prefix);
         plaintext_with_rand_bytes.extend(plaintext);
         plaintext_with_rand_bytes.extend(suffix);
@@ -104,52 +220,69 @@ mod detection_oracle {
             cipher.encrypt(input.as_slice(), output.as_mut_slice())
         } else {
             // encrypt with cbc
-            let random_iv = Block::from(rand::random::<[u8; 16]>());
+            let random_iv = Block::from(rng.gen::<[u8; 16]>());
             cipher = AES128::new(CipherMode::CBC(random_iv), &random_key);
             cipher.encrypt(input.as_slice(), output.as_mut_slice())
         }
 
+        let mode = if encryption_mode_is_ecb {
+            EncryptionMode::Ecb
+        } else {
+            EncryptionMode::Cbc
+        };
+
         // return ciphertext
-        output
+        (mode, output)
     }
 
     /// 2.3
     #[test]
     fn test_random_encryptor() {
         let random_input = [0u8; 64];
-        let ciphertext = random_encryptor(&random_input);
+        let (mode, ciphertext) =
+            random_encryptor_from_rng(&random_input, &mut StdRng::seed_from_u64(23));
         println!("ciphertext:");
         for block in ciphertext.iter() {
             println!("{:?}", block);
         }
 
         println!();
-        if ciphertext[2] == ciphertext[3] {
-            println!("Detected mode: ECB");
+        let detected = if ciphertext[2] == ciphertext[3] {
+            EncryptionMode::Ecb
         } else {
-            println!("Detected mode: CBC");
-        }
+            EncryptionMode::Cbc
+        };
+        println!("Detected mode: {:?}", detected);
+        assert_eq!(detected, mode);
     }
 }
 
 // 2.4
 #[allow(dead_code)]
-mod byte_at_a_time {
+pub(crate) mod byte_at_a_time {
+    use crate::oracle::EncryptionOracle;
     use crate::set_two::aes::{CipherMode, AES128};
     use crate::utils::*;
     use ::aes::Block;
+    use rand::rngs::OsRng;
+    use rand::{Rng, RngCore};
 
-    use super::pkcs_padding;
     pub struct EncryptUnknownString {
         pub(self) key: [u8; 16],
+        /// random bytes put in front of the attacker's input, empty unless `with_random_prefix`
+        pub(self) prefix: Vec<u8>,
         pub(self) unknown_string: Vec<u8>,
         pub(self) cipher: AES128,
     }
 
     impl EncryptUnknownString {
         pub fn new() -> Self {
-            // let key = rand::random::<[u8; 16]>();
-            let key = [1u8; 16];
+            Self::from_rng(&mut OsRng)
+        }
+
+        /// Draws the key from `rng`
+        pub fn from_rng(rng: &mut impl RngCore) -> Self {
+            let key = rng.gen::<[u8; 16]>();
 
             let unknown_string = base64::decode(
                 "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK",
@@ -159,62 +292,79 @@ mod byte_at_a_time {
 
             Self {
                 key,
+                prefix: vec![],
                 unknown_string,
                 cipher,
             }
         }
 
+        /// 2.6: the same oracle, but with a random count of random bytes in front of the input
+        pub fn with_random_prefix() -> Self {
+            Self::with_random_prefix_from_rng(&mut OsRng)
+        }
+
+        /// Draws the key, the prefix length and the prefix from `rng`
+        pub fn with_random_prefix_from_rng(rng: &mut impl RngCore) -> Self {
+            let oracle = Self::from_rng(rng);
+            let prefix_len = rng.gen_range(0..64);
+
+            Self {
+                prefix: (0..prefix_len).map(|_| rng.gen::<u8>()).collect(),
+                ..oracle
+            }
+        }
+
+        /// What the attack is trying to recover, for checking its answer
+        pub fn unknown_string(&self) -> &[u8] {
+            &self.unknown_string
+        }
+
         pub fn generate_ciphertext(&self, plaintext: &[u8]) -> Vec<Block> {
-            let mut modified_input =
-                Vec::<u8>::with_capacity(plaintext.len() + self.unknown_string.len());
+            let mut modified_input = Vec::<u8>::with_capacity(
+                self.prefix.len() + plaintext.len() + self.unknown_string.len(),
+            );
+            modified_input.extend_from_slice(&self.prefix);
             modified_input.extend_from_slice(plaintext);
             modified_input.extend_from_slice(&self.unknown_string);
-            let mod_slice = pkcs_padding(modified_input.as_slice(), 16);
 
-            let (input, mut output) = text_to_io(mod_slice);
+            // text_to_io applies the PKCS#7 padding
+            let (input, mut output) = text_to_io(modified_input);
             self.cipher.encrypt(input.as_slice(), output.as_mut_slice());
             output
         }
     }
 
+    impl Default for EncryptUnknownString {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl EncryptionOracle for EncryptUnknownString {
+        fn encrypt(&mut self, plaintext: &[u8]) -> Vec<u8> {
+            self.generate_ciphertext(plaintext)
+                .into_iter()
+                .flatten()
+                .collect()
+        }
+    }
+
     #[test]
     fn byte_at_a_time_ecb() {
+        use crate::chosen_plaintext::discover_layout;
         use ::aes::Block;
         use itertools::Itertools;
+        use rand::{rngs::StdRng, SeedableRng};
 
-        let encryptor = EncryptUnknownString::new();
-
-        // find the block size and offset
-        let mut block_size = 0usize;
-        let mut offset = 0u8;
-        let prev_ciphertext_size = encryptor
-            .generate_ciphertext("A".as_bytes())
-            .into_iter()
-            .fold(Vec::<u8>::new(), |mut acc, block| {
-                acc.extend_from_slice(block.as_slice());
-                acc
-            })
-            .len();
-        for i in 0..128u8 {
-            let ciphertext = encryptor
-                .generate_ciphertext("A".repeat(i as usize).as_bytes())
-                .into_iter()
-                .fold(Vec::<u8>::new(), |mut acc, block| {
-                    acc.extend_from_slice(block.as_slice());
-                    acc
-                })
-                .len();
-
-            if ciphertext > prev_ciphertext_size {
-                block_size = ciphertext - prev_ciphertext_size;
-                offset = i;
-                break;
-            }
-        }
+        let mut encryptor = EncryptUnknownString::from_rng(&mut StdRng::seed_from_u64(24));
 
-        // print block size and offset
-        println!("block size: {}", block_size);
-        println!("offset: {}", offset);
+        // find the block size and how long the unknown string is
+        let layout = discover_layout(&mut encryptor).unwrap();
+        let block_size = layout.block_size;
+        println!("{:?}", layout);
+        assert_eq!(layout.block_size, 16);
+        assert_eq!(layout.prefix_len, 0);
+        assert_eq!(layout.suffix_len, encryptor.unknown_string.len());
 
         // detect ecb mode
         for combination in encryptor
@@ -234,9 +384,8 @@ mod byte_at_a_time {
         // find the unknown string
         // generate the my_string prefix to the number of blocks the unknown string is
         let secret_block_count = encryptor.generate_ciphertext(&[]).len();
-        let mut space_buff = Vec::<u8>::from(
-            " ".repeat(encryptor.generate_ciphertext(&[]).len() * block_size as usize),
-        );
+        let mut space_buff =
+            Vec::<u8>::from(" ".repeat(encryptor.generate_ciphertext(&[]).len() * block_size));
         let mut buff = space_buff.clone();
         let mut decrypted_characters = Vec::<u8>::with_capacity(secret_block_count * block_size);
         let mut encrypted_ciphertexts = Vec::<Block>::with_capacity(space_buff.len());
@@ -295,4 +444,31 @@ mod byte_at_a_time {
             String::from_utf8(decrypted_characters).unwrap(),
         );
     }
+
+    #[test]
+    fn recover_unknown_string() {
+        use crate::chosen_plaintext::recover_ecb_suffix;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut encryptor = EncryptUnknownString::from_rng(&mut StdRng::seed_from_u64(24));
+        let recovered = recover_ecb_suffix(&mut encryptor).unwrap();
+
+        println!("{}", String::from_utf8_lossy(&recovered));
+        assert_eq!(recovered, encryptor.unknown_string);
+    }
+
+    /// 2.6
+    #[test]
+    fn recover_unknown_string_past_random_prefix() {
+        use crate::chosen_plaintext::recover_ecb_suffix;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut encryptor = EncryptUnknownString::with_random_prefix_from_rng(&mut rng);
+            let recovered = recover_ecb_suffix(&mut encryptor).unwrap();
+
+            assert_eq!(recovered, encryptor.unknown_string, "seed {}", seed);
+        }
+    }
 }
@@ -0,0 +1,76 @@
+// Single-byte xor detection (challenge 1.4): solve every record in a dump and rank them by how
+// much their best plaintext looks like the language
+
+pub use single_byte_xor::*;
+
+#[allow(clippy::module_inception)]
+pub mod single_byte_xor {
+    use crate::repeating_key_xor::guess_xord_key_with;
+    use crate::scoring::{LogLikelihood, PlaintextScorer};
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Candidate {
+        /// Index of the line in the input
+        pub line: usize,
+        pub key: u8,
+        pub plaintext: Vec<u8>,
+        /// The scorer's score for `plaintext`, lower is better
+        pub score: f32,
+    }
+
+    /// Solves every line as single-byte xor and returns them all, most plausible first.
+    /// Scores with `LogLikelihood`, which is comparable between lines of different lengths.
+    pub fn detect_single_byte_xor(lines: &[Vec<u8>]) -> Vec<Candidate> {
+        detect_single_byte_xor_with(lines, &LogLikelihood::default())
+    }
+
+    /// Same as `detect_single_byte_xor`, ranking by `scorer`
+    pub fn detect_single_byte_xor_with(
+        lines: &[Vec<u8>],
+        scorer: &(impl PlaintextScorer + ?Sized),
+    ) -> Vec<Candidate> {
+        let mut candidates = lines
+            .iter()
+            .enumerate()
+            .map(|(line, ciphertext)| {
+                let key = guess_xord_key_with(ciphertext, scorer);
+                let plaintext = ciphertext
+                    .iter()
+                    .map(|byte| byte ^ key)
+                    .collect::<Vec<u8>>();
+
+                Candidate {
+                    line,
+                    key,
+                    score: scorer.score(&plaintext),
+                    plaintext,
+                }
+            })
+            .collect::<Vec<Candidate>>();
+
+        candidates.sort_by(|a, b| a.score.total_cmp(&b.score).then(a.line.cmp(&b.line)));
+        candidates
+    }
+
+    #[test]
+    fn finds_challenge_1_4_line() {
+        use crate::scoring::{ChiSquared, LetterFrequency};
+        use crate::utils::try_parse_file_hex_lines;
+
+        let lines = try_parse_file_hex_lines("src/set_one/1-4.txt").unwrap();
+        let scorers: [&dyn PlaintextScorer; 3] = [
+            &LetterFrequency::default(),
+            &ChiSquared::default(),
+            &LogLikelihood::default(),
+        ];
+
+        for scorer in scorers {
+            let candidates = detect_single_byte_xor_with(&lines, scorer);
+            println!("{:?}", String::from_utf8_lossy(&candidates[0].plaintext));
+            assert_eq!(candidates.len(), lines.len());
+            assert_eq!(candidates[0].plaintext, b"Now that the party is jumping\n");
+        }
+
+        assert_eq!(detect_single_byte_xor(&lines)[0].line, 170);
+    }
+}
@@ -0,0 +1,171 @@
+// Counting what an attack costs: queries, bytes and time spent in the oracle, and how often
+// it works when run against lots of fresh oracles
+
+pub use telemetry::*;
+
+#[allow(clippy::module_inception)]
+pub mod telemetry {
+    use crate::oracle::EncryptionOracle;
+    use rand::RngCore;
+    use std::time::{Duration, Instant};
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct OracleStats {
+        pub queries: usize,
+        /// Total length of everything handed to the oracle
+        pub bytes_submitted: usize,
+        /// Wall-clock time spent inside the oracle
+        pub elapsed: Duration,
+    }
+
+    /// Wraps any oracle and records every call made through it.
+    /// Encryption oracles are counted automatically; anything else (padding checks,
+    /// decryption oracles) goes through `record`.
+    pub struct Instrumented<O> {
+        inner: O,
+        stats: OracleStats,
+    }
+
+    impl<O> Instrumented<O> {
+        pub fn new(inner: O) -> Self {
+            Instrumented {
+                inner,
+                stats: OracleStats::default(),
+            }
+        }
+
+        pub fn stats(&self) -> OracleStats {
+            self.stats
+        }
+
+        pub fn inner(&self) -> &O {
+            &self.inner
+        }
+
+        pub fn into_inner(self) -> O {
+            self.inner
+        }
+
+        /// Counts one query of `bytes` bytes and times `query` against the wrapped oracle
+        pub fn record<T>(&mut self, bytes: usize, query: impl FnOnce(&mut O) -> T) -> T {
+            let start = Instant::now();
+            let result = query(&mut self.inner);
+
+            self.stats.queries += 1;
+            self.stats.bytes_submitted += bytes;
+            self.stats.elapsed += start.elapsed();
+            result
+        }
+    }
+
+    impl<O: EncryptionOracle> EncryptionOracle for Instrumented<O> {
+        fn encrypt(&mut self, plaintext: &[u8]) -> Vec<u8> {
+            self.record(plaintext.len(), |oracle| oracle.encrypt(plaintext))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct MonteCarloReport {
+        pub trials: usize,
+        pub successes: usize,
+        pub mean_queries: f64,
+        pub worst_queries: usize,
+        pub mean_bytes_submitted: f64,
+        pub mean_elapsed: Duration,
+    }
+
+    impl MonteCarloReport {
+        pub fn success_rate(&self) -> f64 {
+            self.successes as f64 / self.trials.max(1) as f64
+        }
+    }
+
+    /// Runs `trial` `trials` times. Each run should build a fresh oracle from `rng`, attack it
+    /// through an `Instrumented` wrapper and return whether it worked along with the stats.
+    pub fn monte_carlo<R, F>(trials: usize, rng: &mut R, mut trial: F) -> MonteCarloReport
+    where
+        R: RngCore,
+        F: FnMut(&mut R) -> (bool, OracleStats),
+    {
+        let mut successes = 0;
+        let mut total = OracleStats::default();
+        let mut worst_queries = 0;
+
+        for _ in 0..trials {
+            let (success, stats) = trial(rng);
+
+            successes += success as usize;
+            worst_queries = worst_queries.max(stats.queries);
+            total.queries += stats.queries;
+            total.bytes_submitted += stats.bytes_submitted;
+            total.elapsed += stats.elapsed;
+        }
+
+        let runs = trials.max(1);
+        MonteCarloReport {
+            trials,
+            successes,
+            mean_queries: total.queries as f64 / runs as f64,
+            worst_queries,
+            mean_bytes_submitted: total.bytes_submitted as f64 / runs as f64,
+            mean_elapsed: total.elapsed / runs as u32,
+        }
+    }
+
+    #[test]
+    fn counts_queries_and_bytes() {
+        let mut oracle = Instrumented::new(|plaintext: &[u8]| plaintext.to_vec());
+        oracle.encrypt(b"abc");
+        oracle.encrypt(b"");
+        let checked = oracle.record(5, |_| true);
+
+        assert!(checked);
+        assert_eq!(oracle.stats().queries, 3);
+        assert_eq!(oracle.stats().bytes_submitted, 8);
+
+        let empty = monte_carlo(0, &mut rand::rngs::OsRng, |_| unreachable!());
+        assert_eq!(empty.success_rate(), 0.0);
+        assert_eq!(empty.mean_queries, 0.0);
+    }
+
+    #[test]
+    fn compares_attacks() {
+        use crate::block_cipher::BLOCK_SIZE_BYTES;
+        use crate::chosen_plaintext::recover_ecb_suffix;
+        use crate::oracle::EncryptUnknownString;
+        use crate::padding_oracle::{padding_oracle_attack, PaddingOracleServer};
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(11);
+
+        let byte_at_a_time = monte_carlo(3, &mut rng, |rng| {
+            let mut oracle =
+                Instrumented::new(EncryptUnknownString::with_random_prefix_from_rng(rng));
+            let recovered = recover_ecb_suffix(&mut oracle);
+            let success = recovered.as_deref() == Some(oracle.inner().unknown_string());
+            (success, oracle.stats())
+        });
+
+        let padding_oracle = monte_carlo(3, &mut rng, |rng| {
+            let mut server = PaddingOracleServer::with_random_secret_from_rng(rng);
+            let (iv, ciphertext) = server.encrypt();
+
+            let mut oracle = Instrumented::new(server);
+            let result = padding_oracle_attack(&iv, &ciphertext, |iv, ciphertext| {
+                oracle.record(BLOCK_SIZE_BYTES + ciphertext.len(), |server| {
+                    server.check(iv, ciphertext)
+                })
+            });
+            let success = result.is_ok_and(|result| result.plaintext == oracle.inner().secret());
+            (success, oracle.stats())
+        });
+
+        println!("byte at a time: {:?}", byte_at_a_time);
+        println!("padding oracle: {:?}", padding_oracle);
+        assert_eq!(byte_at_a_time.success_rate(), 1.0);
+        assert_eq!(padding_oracle.success_rate(), 1.0);
+        // two queries per byte plus layout discovery, against up to 256 per byte
+        assert!(byte_at_a_time.mean_queries < padding_oracle.mean_queries);
+        assert!(padding_oracle.worst_queries as f64 >= padding_oracle.mean_queries);
+    }
+}
@@ -1,44 +1,59 @@
 pub use utils::*;
 
+#[allow(clippy::module_inception)]
 pub mod utils {
+    use crate::error::CryptopalsError;
     use ::aes::cipher::generic_array::GenericArray;
     use ::aes::cipher::typenum::U16;
     use ::aes::Block;
     use itertools::Itertools;
     pub fn parse_file_base64(filename: &str) -> Vec<u8> {
+        try_parse_file_base64(filename).unwrap()
+    }
+
+    /// Reads a file of (possibly line-wrapped) base64, reporting io and decoding errors
+    pub fn try_parse_file_base64(filename: &str) -> Result<Vec<u8>, CryptopalsError> {
         use std::fs;
 
-        let file = fs::read_to_string(filename).unwrap();
-        base64::decode(file.replace('\n', "").as_bytes()).unwrap()
+        let file = fs::read_to_string(filename)?;
+        Ok(base64::decode(file.replace('\n', "").as_bytes())?)
+    }
+
+    pub fn parse_file_hex_lines(filename: &str) -> Vec<Vec<u8>> {
+        try_parse_file_hex_lines(filename).unwrap()
+    }
+
+    /// Reads a file with one hex record per line, skipping blank lines
+    pub fn try_parse_file_hex_lines(filename: &str) -> Result<Vec<Vec<u8>>, CryptopalsError> {
+        use std::fs;
+
+        fs::read_to_string(filename)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| Ok(hex::decode(line)?))
+            .collect()
     }
 
     /// this generates an output buffer from the block count (assuming 16-byte blocks)
     #[allow(dead_code)]
     pub fn output_from_block_count(block_count: usize) -> Vec<GenericArray<u8, U16>> {
-        let mut empty_buffer = Vec::<u8>::with_capacity(block_count * 16);
-        for _ in 0..(block_count * 16) {
-            empty_buffer.push(0);
-        }
+        let empty_buffer = vec![0u8; block_count * 16];
         let output = empty_buffer
             .chunks(16)
-            .into_iter()
             .map(GenericArray::<u8, U16>::clone_from_slice)
             .collect_vec();
 
         output
     }
 
-    /// This turns a slice of bytes (eg ciphertext) into 16 byte blocks ready for encryption;
+    /// This turns a slice of bytes (eg plaintext) into PKCS#7 padded 16 byte blocks ready for encryption;
     #[allow(dead_code)]
     pub fn input_to_padded_blocks(input: &[u8]) -> Vec<GenericArray<u8, U16>> {
-        let mut output = Vec::<u8>::with_capacity(input.len());
-        output.extend_from_slice(input);
-        let padding = 16 - (input.len() % 16);
-        output.extend(vec![42u8; padding]);
+        let output = crate::block_cipher::pkcs_padding(input, 16);
 
         let blocks = output
             .chunks(16)
-            .into_iter()
             .map(GenericArray::<u8, U16>::clone_from_slice)
             .collect_vec();
 
@@ -50,4 +65,15 @@ pub mod utils {
         let output = output_from_block_count(input.len());
         (input, output)
     }
+
+    /// The lyrics hidden in 1.7, a decent pile of english for tests to train on and encrypt
+    #[cfg(test)]
+    pub fn lyrics() -> Vec<u8> {
+        use crate::block_cipher::{BlockCipher, Ecb};
+
+        BlockCipher::new(Ecb, b"YELLOW SUBMARINE")
+            .unwrap()
+            .decrypt_bytes(&parse_file_base64("src/set_one/1-7.txt"))
+            .unwrap()
+    }
 }